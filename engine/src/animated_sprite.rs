@@ -1,4 +1,4 @@
-use drawable::{DrawContext, Drawable};
+use drawable::{DrawContext, DrawParams, Drawable};
 use texture_registry::Texture;
 
 use super::Error;
@@ -6,6 +6,8 @@ use vector::Vec2;
 use transform::Transform;
 use extent::Extent;
 use offset::Offset;
+use BlendMode;
+use Color;
 
 #[derive(Clone)]
 pub struct AnimatedSprite {
@@ -15,7 +17,8 @@ pub struct AnimatedSprite {
     current_frame: f32,
     mode_count: i32,
     frame_count: i32,
-    transform: Transform
+    transform: Transform,
+    draw_params: DrawParams
 }
 
 pub trait SpriteTrait : Drawable + 'static {
@@ -27,6 +30,9 @@ pub trait SpriteTrait : Drawable + 'static {
     fn set_scale(&mut self, scale: f32);
 
     fn calculate_size(&self) -> Vec2;
+
+    fn get_draw_params(&self) -> DrawParams;
+    fn set_draw_params(&mut self, draw_params: DrawParams);
 }
 
 pub trait Animatable : Drawable + 'static {
@@ -57,6 +63,7 @@ impl AnimatedSprite {
                 mode_count,
                 frame_count,
                 transform: Transform::new(),
+                draw_params: DrawParams::new(),
             };
 
         Ok(animated_sprite)
@@ -66,12 +73,44 @@ impl AnimatedSprite {
         Vec2::from_coords(self.tile_extent.width as f32, self.tile_extent.height as f32) * self.transform.get_scale()
     }
 
+    // Every frame of one mode, e.g. for particles
+    pub fn get_frames(&self, mode: i32) -> Vec<Texture> {
+        (0..self.frame_count)
+            .map(
+                |frame| {
+                    let offset = Offset::from_coords(frame * self.tile_extent.width, mode * self.tile_extent.height);
+                    self.texture.sub_texture(offset, self.tile_extent).unwrap()
+                }
+            )
+            .collect()
+    }
+
+    pub fn set_color_mod(&mut self, color: Color) {
+        self.draw_params.color = color;
+    }
+
+    pub fn set_alpha(&mut self, alpha: u8) {
+        self.draw_params.alpha = alpha;
+    }
+
+    // Lets one row of frames be used for both facing directions
+    pub fn set_flip(&mut self, horizontal: bool, vertical: bool) {
+        self.draw_params.flip_horizontal = horizontal;
+        self.draw_params.flip_vertical = vertical;
+    }
+
+    pub fn set_blend_mode(&mut self, blend_mode: BlendMode) {
+        self.draw_params.blend_mode = Some(blend_mode);
+    }
+
 }
 
 impl Animatable for AnimatedSprite {
+    // Modes out of range are ignored in release builds
     fn set_mode(&mut self, mode: i32) {
+        debug_assert!(mode >= 0 && mode < self.mode_count, "Sprite mode {} out of range", mode);
         if mode < 0 || mode >= self.mode_count {
-            panic!("Mode out of range");
+            return;
         }
         self.current_mode = mode;
     }
@@ -107,6 +146,14 @@ impl SpriteTrait for AnimatedSprite {
     fn calculate_size(&self) -> Vec2 {
         self.tile_extent.to_vec() * self.transform.get_scale()
     }
+
+    fn get_draw_params(&self) -> DrawParams {
+        self.draw_params
+    }
+
+    fn set_draw_params(&mut self, draw_params: DrawParams) {
+        self.draw_params = draw_params;
+    }
 }
 
 impl Drawable for AnimatedSprite {
@@ -121,7 +168,7 @@ impl Drawable for AnimatedSprite {
         let extent = Extent::new(self.tile_extent.width, self.tile_extent.height);
         let sub_texture = self.texture.sub_texture(offset, extent).unwrap();
 
-        ctx.draw(&sub_texture, &self.transform);
+        ctx.draw_with(&sub_texture, &self.transform, &self.draw_params);
     }
 }
 
@@ -163,6 +210,11 @@ impl Drawable for AggregatedAnimatedSprite {
 
 impl Animatable for AggregatedAnimatedSprite {
     fn set_mode(&mut self, mode: i32) {
+        debug_assert!(mode >= 0 && mode < self.get_mode_count(), "Sprite mode {} out of range", mode);
+        if mode < 0 || mode >= self.get_mode_count() {
+            return;
+        }
+
         self.mode = mode;
         for (index, sprite) in self.sprites.iter().enumerate() {
             if self.mode >= sprite.get_mode_count() {
@@ -210,4 +262,15 @@ impl SpriteTrait for AggregatedAnimatedSprite
     fn calculate_size(&self) -> Vec2 {
         self.sprites[self.sprite_index as usize].calculate_size()
     }
+
+    fn get_draw_params(&self) -> DrawParams {
+        self.sprites[self.sprite_index as usize].get_draw_params()
+    }
+
+    // Applies to all sprites, so switching mode keeps the tint
+    fn set_draw_params(&mut self, draw_params: DrawParams) {
+        for sprite in self.sprites.iter_mut() {
+            sprite.set_draw_params(draw_params);
+        }
+    }
 }
@@ -0,0 +1,258 @@
+use std::collections::HashMap;
+
+use animated_sprite::Animatable;
+use Error;
+
+// Checks on the parameters of an AnimationStateMachine. Parameters that were
+// never set are 0.0, flags are 1.0 when set.
+#[derive(Clone, Debug)]
+pub enum Condition {
+    Above(String, f32),
+    Below(String, f32),
+    IsSet(String),
+    IsNotSet(String),
+}
+
+impl Condition {
+    fn holds(&self, parameters: &HashMap<String, f32>) -> bool {
+        let value = |name: &String| parameters.get(name).cloned().unwrap_or(0.0);
+
+        match *self {
+            Condition::Above(ref name, limit) => value(name) > limit,
+            Condition::Below(ref name, limit) => value(name) < limit,
+            Condition::IsSet(ref name) => value(name) != 0.0,
+            Condition::IsNotSet(ref name) => value(name) == 0.0,
+        }
+    }
+}
+
+// Moves from one state to another once all conditions hold
+#[derive(Clone, Debug)]
+pub struct Transition {
+    from: Option<String>,
+    to: String,
+    conditions: Vec<Condition>,
+    min_time: f32,
+}
+
+impl Transition {
+    pub fn new(from: &str, to: &str) -> Transition {
+        Transition {
+            from: Some(from.to_string()),
+            to: to.to_string(),
+            conditions: Vec::new(),
+            min_time: 0.0,
+        }
+    }
+
+    // Taken from whatever state the machine is in, except `to` itself
+    pub fn from_any(to: &str) -> Transition {
+        Transition {
+            from: None,
+            to: to.to_string(),
+            conditions: Vec::new(),
+            min_time: 0.0,
+        }
+    }
+
+    pub fn with_condition(mut self, condition: Condition) -> Transition {
+        self.conditions.push(condition);
+        self
+    }
+
+    // Seconds the machine has to stay in the current state first, so short
+    // flickers of a parameter do not restart clips
+    pub fn with_min_time(mut self, min_time: f32) -> Transition {
+        self.min_time = min_time;
+        self
+    }
+}
+
+struct State {
+    name: String,
+    mode: i32,
+}
+
+// Picks the mode of an Animatable from parameters the object sets every
+// update, instead of working it out by hand:
+//
+//     let mut states = AnimationStateMachine::new("idle", 0);
+//     states.add_state("walk", 4)?;
+//     states.add_transition(Transition::new("idle", "walk").with_condition(Condition::Above("speed".to_string(), 0.1)))?;
+//     states.add_transition(Transition::new("walk", "idle").with_condition(Condition::Below("speed".to_string(), 0.1)))?;
+//
+//     states.set_parameter("speed", velocity.len());
+//     states.update(dt);
+//     states.apply(&mut sprite);
+//
+// Switching states is a hard cut to the new mode, there is no blending.
+pub struct AnimationStateMachine {
+    states: Vec<State>,
+    transitions: Vec<(Option<usize>, usize, Transition)>,
+    parameters: HashMap<String, f32>,
+    current: usize,
+    time_in_state: f32,
+}
+
+impl AnimationStateMachine {
+    pub fn new(initial_state: &str, mode: i32) -> AnimationStateMachine {
+        AnimationStateMachine {
+            states: vec![State { name: initial_state.to_string(), mode }],
+            transitions: Vec::new(),
+            parameters: HashMap::new(),
+            current: 0,
+            time_in_state: 0.0,
+        }
+    }
+
+    pub fn add_state(&mut self, name: &str, mode: i32) -> Result<(), Error> {
+        if self.find_state(name).is_some() {
+            return Err(Error::FatalError(format!("Animation state {} already exists", name)));
+        }
+
+        self.states.push(State { name: name.to_string(), mode });
+        Ok(())
+    }
+
+    // Transitions are checked in the order they were added
+    pub fn add_transition(&mut self, transition: Transition) -> Result<(), Error> {
+        let state_index = |name: &str| {
+            self.find_state(name)
+                .ok_or_else(|| Error::FatalError(format!("Unknown animation state {}", name)))
+        };
+
+        let from =
+            match transition.from {
+                Some(ref from) => Some(state_index(from)?),
+                None => None
+            };
+        let to = state_index(&transition.to)?;
+
+        self.transitions.push((from, to, transition));
+        Ok(())
+    }
+
+    pub fn set_parameter(&mut self, name: &str, value: f32) {
+        self.parameters.insert(name.to_string(), value);
+    }
+
+    pub fn get_parameter(&self, name: &str) -> f32 {
+        self.parameters.get(name).cloned().unwrap_or(0.0)
+    }
+
+    pub fn set_flag(&mut self, name: &str, value: bool) {
+        self.set_parameter(name, if value { 1.0 } else { 0.0 });
+    }
+
+    pub fn get_flag(&self, name: &str) -> bool {
+        self.get_parameter(name) != 0.0
+    }
+
+    pub fn get_state(&self) -> &str {
+        &self.states[self.current].name
+    }
+
+    // Jumps straight to the state, ignoring transitions
+    pub fn set_state(&mut self, name: &str) -> Result<(), Error> {
+        let index =
+            self.find_state(name)
+                .ok_or_else(|| Error::FatalError(format!("Unknown animation state {}", name)))?;
+
+        if index != self.current {
+            self.current = index;
+            self.time_in_state = 0.0;
+        }
+
+        Ok(())
+    }
+
+    pub fn get_mode(&self) -> i32 {
+        self.states[self.current].mode
+    }
+
+    pub fn get_time_in_state(&self) -> f32 {
+        self.time_in_state
+    }
+
+    // Takes at most one transition. Returns true if the state changed.
+    pub fn update(&mut self, dt: f32) -> bool {
+        self.time_in_state += dt;
+
+        let current = self.current;
+        let time_in_state = self.time_in_state;
+        let parameters = &self.parameters;
+
+        let next =
+            self.transitions.iter()
+                .find(|&&(from, to, ref transition)| {
+                    from.map(|from| from == current).unwrap_or(to != current) &&
+                        time_in_state >= transition.min_time &&
+                        transition.conditions.iter().all(|condition| condition.holds(parameters))
+                })
+                .map(|&(_, to, _)| to);
+
+        match next {
+            Some(next) => {
+                self.current = next;
+                self.time_in_state = 0.0;
+                true
+            },
+            None => false
+        }
+    }
+
+    // Sets the mode of the current state on the sprite, which also works for
+    // AggregatedAnimatedSprite and the clips of a SpriteAnimation
+    pub fn apply<A: Animatable + ?Sized>(&self, sprite: &mut A) {
+        sprite.set_mode(self.get_mode());
+    }
+
+    // For sheets with one row of modes per facing direction or similar
+    pub fn apply_with_offset<A: Animatable + ?Sized>(&self, sprite: &mut A, offset: i32) {
+        sprite.set_mode(self.get_mode() + offset);
+    }
+
+    fn find_state(&self, name: &str) -> Option<usize> {
+        self.states.iter().position(|state| state.name == name)
+    }
+}
+
+#[test]
+fn test_state_machine_transitions() {
+    let mut states = AnimationStateMachine::new("idle", 0);
+    states.add_state("walk", 4).unwrap();
+    states.add_state("hit", 8).unwrap();
+
+    states.add_transition(Transition::from_any("hit").with_condition(Condition::IsSet("hit".to_string()))).unwrap();
+    states.add_transition(Transition::new("hit", "idle").with_min_time(0.5)).unwrap();
+    states.add_transition(Transition::new("idle", "walk").with_condition(Condition::Above("speed".to_string(), 0.1))).unwrap();
+    states.add_transition(Transition::new("walk", "idle").with_condition(Condition::Below("speed".to_string(), 0.1))).unwrap();
+    assert!(states.add_transition(Transition::new("idle", "jump")).is_err());
+
+    assert!(!states.update(0.1));
+
+    states.set_parameter("speed", 1.0);
+    assert!(states.update(0.1));
+    assert_eq!(states.get_state(), "walk");
+    assert_eq!(states.get_mode(), 4);
+
+    states.set_flag("hit", true);
+    assert!(states.update(0.1));
+    assert_eq!(states.get_state(), "hit");
+
+    // Does not enter itself again while the flag is still set, so the time
+    // in the state keeps counting towards leaving it
+    assert!(!states.update(0.1));
+    assert_eq!(states.get_state(), "hit");
+    assert!(states.update(0.5));
+    assert_eq!(states.get_state(), "idle");
+
+    // Any other state goes back to it though
+    assert!(states.update(0.1));
+    assert_eq!(states.get_state(), "hit");
+
+    states.set_flag("hit", false);
+    assert!(!states.update(0.1));
+    assert!(states.update(0.5));
+    assert_eq!(states.get_state(), "idle");
+}
@@ -0,0 +1,15 @@
+use std::any::Any;
+
+// Lets trait objects be downcast to their concrete type without every
+// implementor having to write the boilerplate.
+pub trait AsAny {
+    fn as_any(&self) -> &dyn Any;
+
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<T: Any> AsAny for T {
+    fn as_any(&self) -> &dyn Any { self }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any { self }
+}
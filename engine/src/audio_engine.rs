@@ -256,6 +256,7 @@ pub struct AudioEngine {
     _audio_device: AudioDevice<AudioMixer>,
     mixer: AudioMixer,
     _sound_map: HashMap<u64, Vec<f32>>,
+    sound_files: HashMap<u64, String>,
 }
 
 
@@ -284,6 +285,7 @@ impl AudioEngine {
             _audio_device: device,
             mixer,
             _sound_map: HashMap::new(),
+            sound_files: HashMap::new(),
         }
     }
 
@@ -316,37 +318,41 @@ impl AudioEngine {
 
     }
     pub fn pre_load_file<T: Hash>(&mut self, key: T,  filename: &str) -> Result<(), Error> {
-        use std::slice;
-        use std::mem;
-        use std::i16;
+        let pcm_mono_float = load_wav(filename)?;
+        let hash = self.get_hash(key);
+        self._sound_map.insert(hash, pcm_mono_float);
+        self.sound_files.insert(hash, filename.to_string());
+        Ok(())
+    }
 
-        let wav = AudioSpecWAV::load_wav(filename)?;
+    pub fn get_loaded_files(&self) -> Vec<String> {
+        let mut files : Vec<String> = self.sound_files.values().cloned().collect();
+        files.sort();
+        files.dedup();
+        files
+    }
 
-        if wav.format != AudioFormat::S16LSB {
-            return Err(Error::WavError(WavError::Not16Bit));
-        }
+    // Reads the file again for every key it was loaded with. Sounds that are
+    // already playing keep the old samples. Returns false if the file was
+    // never loaded.
+    pub fn reload_file(&mut self, filename: &str) -> Result<bool, Error> {
+        let hashes : Vec<u64> =
+            self.sound_files.iter()
+                .filter(|(_, f)| f.as_str() == filename)
+                .map(|(hash, _)| *hash)
+                .collect();
 
-        if wav.channels != 2 {
-            return Err(Error::WavError(WavError::NotStereo));
+        if hashes.is_empty() {
+            return Ok(false);
         }
 
-        if wav.freq != 44100 {
-            return Err(Error::WavError(WavError::Not44100Hz));
-        }
+        let pcm_mono_float = load_wav(filename)?;
 
-        let pcm_stereo_16 : &[i16]= unsafe {
-            slice::from_raw_parts(
-                mem::transmute(wav.buffer().as_ptr()),
-                wav.buffer().len() / 2
-            )
-        };
-
-        let pcm_stereo_float : Vec<f32> = pcm_stereo_16.iter().map(|x| (*x as f32) / (i16::MAX as f32)).collect();
+        for hash in hashes {
+            self._sound_map.insert(hash, pcm_mono_float.clone());
+        }
 
-        let pcm_mono_float = pcm_stereo_float.chunks(2).map(|lr| (lr[0] + lr[1]) / 2.0).collect();
-        let hash = self.get_hash(key);
-        self._sound_map.insert(hash, pcm_mono_float);
-        Ok(())
+        Ok(true)
     }
 
     fn get_hash<T: Hash>(&self, t: T) -> u64 {
@@ -421,3 +427,35 @@ impl AudioEngine {
     }
 
 }
+
+// Samples of a 16 bit stereo WAV at 44100 Hz, mixed down to mono
+fn load_wav(filename: &str) -> Result<Vec<f32>, Error> {
+    use std::slice;
+    use std::mem;
+    use std::i16;
+
+    let wav = AudioSpecWAV::load_wav(filename)?;
+
+    if wav.format != AudioFormat::S16LSB {
+        return Err(Error::WavError(WavError::Not16Bit));
+    }
+
+    if wav.channels != 2 {
+        return Err(Error::WavError(WavError::NotStereo));
+    }
+
+    if wav.freq != 44100 {
+        return Err(Error::WavError(WavError::Not44100Hz));
+    }
+
+    let pcm_stereo_16 : &[i16]= unsafe {
+        slice::from_raw_parts(
+            mem::transmute(wav.buffer().as_ptr()),
+            wav.buffer().len() / 2
+        )
+    };
+
+    let pcm_stereo_float : Vec<f32> = pcm_stereo_16.iter().map(|x| (*x as f32) / (i16::MAX as f32)).collect();
+
+    Ok(pcm_stereo_float.chunks(2).map(|lr| (lr[0] + lr[1]) / 2.0).collect())
+}
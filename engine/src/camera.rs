@@ -0,0 +1,343 @@
+use rand::Rng;
+use rand;
+
+use rect::Rect2D;
+use scene::{Scene, SceneObjectId};
+use vector::Vec2;
+use Engine;
+
+struct ZoomToFit {
+    margin: f32,
+    min_zoom: f32,
+    max_zoom: f32,
+}
+
+// Moves the engine camera towards whatever it is told to look at. Every
+// state used to set the camera position straight from the player, this
+// adds smoothing, a dead-zone, level bounds, screen shake and framing of
+// several targets at once.
+//
+//     let mut camera =
+//         CameraController::new()
+//             .with_follow_speed(4.0)
+//             .with_dead_zone(Rect2D::centered_square(120.0))
+//             .with_bounds(level.get_bounds());
+//
+//     camera.set_target(player_position);
+//     camera.update(ctx, dt);
+pub struct CameraController {
+    targets: Vec<Vec2>,
+    position: Vec2,
+    zoom: f32,
+    target_zoom: f32,
+    follow_speed: f32,
+    zoom_speed: f32,
+    dead_zone: Option<Rect2D>,
+    bounds: Option<Rect2D>,
+    fit: Option<ZoomToFit>,
+    trauma: f32,
+    trauma_decay: f32,
+    max_shake_offset: f32,
+}
+
+impl Default for CameraController {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CameraController {
+    pub fn new() -> CameraController {
+        CameraController {
+            targets: Vec::new(),
+            position: Vec2::new(),
+            zoom: 1.0,
+            target_zoom: 1.0,
+            follow_speed: 0.0,
+            zoom_speed: 0.0,
+            dead_zone: None,
+            bounds: None,
+            fit: None,
+            trauma: 0.0,
+            trauma_decay: 1.0,
+            max_shake_offset: 30.0,
+        }
+    }
+
+    pub fn with_position(mut self, position: Vec2) -> CameraController {
+        self.position = position;
+        self
+    }
+
+    pub fn with_zoom(mut self, zoom: f32) -> CameraController {
+        self.zoom = zoom;
+        self.target_zoom = zoom;
+        self
+    }
+
+    // Speeds of zero snap straight to the target
+    pub fn with_follow_speed(mut self, follow_speed: f32) -> CameraController {
+        self.follow_speed = follow_speed;
+        self
+    }
+
+    pub fn with_zoom_speed(mut self, zoom_speed: f32) -> CameraController {
+        self.zoom_speed = zoom_speed;
+        self
+    }
+
+    // The camera stays put while the target is inside this rectangle, which
+    // is relative to the camera position and in world units
+    pub fn with_dead_zone(mut self, dead_zone: Rect2D) -> CameraController {
+        self.dead_zone = Some(dead_zone);
+        self
+    }
+
+    pub fn with_bounds(mut self, bounds: Rect2D) -> CameraController {
+        self.bounds = Some(bounds);
+        self
+    }
+
+    // Zooms so that all targets are visible with `margin` world units to
+    // spare around them
+    pub fn with_zoom_to_fit(mut self, margin: f32, min_zoom: f32, max_zoom: f32) -> CameraController {
+        self.fit = Some(ZoomToFit { margin, min_zoom, max_zoom });
+        self
+    }
+
+    // `decay` is how much trauma is lost per second
+    pub fn with_shake(mut self, max_offset: f32, decay: f32) -> CameraController {
+        self.max_shake_offset = max_offset;
+        self.trauma_decay = decay;
+        self
+    }
+
+    pub fn set_target(&mut self, target: Vec2) {
+        self.targets.clear();
+        self.targets.push(target);
+    }
+
+    pub fn set_targets(&mut self, targets: &[Vec2]) {
+        self.targets = targets.to_vec();
+    }
+
+    // Objects without a position are left out
+    pub fn set_target_objects(&mut self, scene: &Scene, ids: &[SceneObjectId]) {
+        self.targets = ids.iter().filter_map(|id| scene.get_position(*id)).collect();
+    }
+
+    pub fn clear_targets(&mut self) {
+        self.targets.clear();
+    }
+
+    pub fn set_zoom(&mut self, zoom: f32) {
+        self.target_zoom = zoom;
+    }
+
+    pub fn get_zoom(&self) -> f32 {
+        self.zoom
+    }
+
+    pub fn get_position(&self) -> Vec2 {
+        self.position
+    }
+
+    pub fn set_dead_zone(&mut self, dead_zone: Option<Rect2D>) {
+        self.dead_zone = dead_zone;
+    }
+
+    pub fn set_bounds(&mut self, bounds: Option<Rect2D>) {
+        self.bounds = bounds;
+    }
+
+    // Trauma is between 0 and 1, the shake grows with its square
+    pub fn add_trauma(&mut self, amount: f32) {
+        self.trauma = (self.trauma + amount).clamp(0.0, 1.0);
+    }
+
+    pub fn get_trauma(&self) -> f32 {
+        self.trauma
+    }
+
+    // Jumps to the target instead of easing there, e.g. after a level change
+    pub fn snap(&mut self, ctx: &mut Engine) {
+        let (follow_speed, zoom_speed) = (self.follow_speed, self.zoom_speed);
+        self.follow_speed = 0.0;
+        self.zoom_speed = 0.0;
+        self.update(ctx, 0.0);
+        self.follow_speed = follow_speed;
+        self.zoom_speed = zoom_speed;
+    }
+
+    pub fn update(&mut self, ctx: &mut Engine, dt: f32) {
+        // Visible world size at zoom 1.0
+        let unit_view = ctx.get_screen_bounds().max * ctx.get_window_scale();
+
+        let position = self.advance(unit_view, dt);
+
+        ctx.set_camera_zoom(self.zoom);
+        ctx.set_camera_position(position);
+    }
+
+    // Same as update, but moves the camera of a viewport, for split-screen
+    pub fn update_viewport(&mut self, ctx: &mut Engine, name: &str, dt: f32) {
+        let screen_bounds = ctx.get_screen_bounds();
+        let window_scale = ctx.get_window_scale();
+
+        let unit_view =
+            match ctx.get_viewport(name) {
+                Some(viewport) => {
+                    let rect = viewport.get_screen_rect(screen_bounds);
+                    (rect.max - rect.min) * window_scale
+                },
+                None => { return; }
+            };
+
+        let position = self.advance(unit_view, dt);
+
+        ctx.set_viewport_camera_zoom(name, self.zoom);
+        ctx.set_viewport_camera_position(name, position);
+    }
+
+    // Returns where the camera should be put, shake included
+    fn advance(&mut self, unit_view: Vec2, dt: f32) -> Vec2 {
+
+        let mut focus = None;
+
+        if !self.targets.is_empty() {
+            let mut frame = Rect2D::empty();
+            for target in self.targets.iter() {
+                frame.expand(*target);
+            }
+
+            if let Some(ref fit) = self.fit {
+                let width = frame.width() + fit.margin * 2.0;
+                let height = frame.height() + fit.margin * 2.0;
+                let zoom = (width / unit_view.x).max(height / unit_view.y);
+                self.target_zoom = zoom.clamp(fit.min_zoom, fit.max_zoom);
+            }
+
+            focus = Some(frame.center());
+        }
+
+        self.zoom = approach(self.zoom, self.target_zoom, self.zoom_speed, dt);
+
+        if let Some(focus) = focus {
+            let mut desired = focus;
+
+            if let Some(dead_zone) = self.dead_zone {
+                let offset = focus - self.position;
+                desired = self.position;
+
+                if offset.x < dead_zone.min.x { desired.x = focus.x - dead_zone.min.x; }
+                if offset.x > dead_zone.max.x { desired.x = focus.x - dead_zone.max.x; }
+                if offset.y < dead_zone.min.y { desired.y = focus.y - dead_zone.min.y; }
+                if offset.y > dead_zone.max.y { desired.y = focus.y - dead_zone.max.y; }
+            }
+
+            self.position.x = approach(self.position.x, desired.x, self.follow_speed, dt);
+            self.position.y = approach(self.position.y, desired.y, self.follow_speed, dt);
+        }
+
+        if let Some(bounds) = self.bounds {
+            let half_view = unit_view * (self.zoom * 0.5);
+            self.position.x = clamp_to_bounds(self.position.x, half_view.x, bounds.min.x, bounds.max.x);
+            self.position.y = clamp_to_bounds(self.position.y, half_view.y, bounds.min.y, bounds.max.y);
+        }
+
+        let mut shake_offset = Vec2::new();
+
+        if self.trauma > 0.0 {
+            let shake = self.trauma * self.trauma;
+            let mut rng = rand::thread_rng();
+            let direction = Vec2::from_coords(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0));
+            shake_offset = direction * (self.max_shake_offset * shake * self.zoom);
+
+            self.trauma = (self.trauma - self.trauma_decay * dt).max(0.0);
+        }
+
+        self.position + shake_offset
+    }
+}
+
+fn approach(current: f32, target: f32, speed: f32, dt: f32) -> f32 {
+    if speed <= 0.0 {
+        return target;
+    }
+
+    // Frame rate independent exponential smoothing
+    let factor = 1.0 - (-speed * dt).exp();
+    current + (target - current) * factor
+}
+
+// Centers on the bounds when they are smaller than the view
+fn clamp_to_bounds(position: f32, half_view: f32, min: f32, max: f32) -> f32 {
+    if max - min <= half_view * 2.0 {
+        (min + max) * 0.5
+    } else {
+        position.clamp(min + half_view, max - half_view)
+    }
+}
+
+#[test]
+fn test_framing() {
+    // World units visible at zoom 1.0
+    let unit_view = Vec2::from_coords(100.0, 50.0);
+
+    // The camera stays put until the target leaves the dead-zone, then it
+    // moves just far enough to keep it on the edge
+    let mut camera =
+        CameraController::new()
+            .with_dead_zone(Rect2D::new(Vec2::from_coords(-10.0, -10.0), Vec2::from_coords(10.0, 10.0)));
+
+    camera.set_target(Vec2::from_coords(8.0, -5.0));
+    assert_eq!(camera.advance(unit_view, 0.1), Vec2::new());
+
+    camera.set_target(Vec2::from_coords(30.0, -5.0));
+    assert_eq!(camera.advance(unit_view, 0.1), Vec2::from_coords(20.0, 0.0));
+
+    // At zoom 2.0 half the view is 100 by 50, which is more than the bounds
+    // are high, so the camera is centered on them vertically
+    let mut camera =
+        CameraController::new()
+            .with_zoom(2.0)
+            .with_bounds(Rect2D::new(Vec2::new(), Vec2::from_coords(500.0, 80.0)));
+
+    camera.set_target(Vec2::from_coords(20.0, 0.0));
+    assert_eq!(camera.advance(unit_view, 0.1), Vec2::from_coords(100.0, 40.0));
+
+    camera.set_target(Vec2::from_coords(1000.0, 0.0));
+    assert_eq!(camera.advance(unit_view, 0.1), Vec2::from_coords(400.0, 40.0));
+
+    // Zoom to fit frames the middle of all targets, within the zoom limits
+    let mut camera = CameraController::new().with_zoom_to_fit(50.0, 0.5, 3.0);
+
+    camera.set_targets(&[Vec2::new(), Vec2::from_coords(100.0, 20.0)]);
+    assert_eq!(camera.advance(unit_view, 0.1), Vec2::from_coords(50.0, 10.0));
+    assert_eq!(camera.get_zoom(), 2.4);
+
+    camera.set_targets(&[Vec2::new(), Vec2::from_coords(400.0, 0.0)]);
+    camera.advance(unit_view, 0.1);
+    assert_eq!(camera.get_zoom(), 3.0);
+}
+
+#[test]
+fn test_shake_decay() {
+    let unit_view = Vec2::from_coords(100.0, 50.0);
+    let mut camera = CameraController::new().with_shake(10.0, 0.5);
+
+    camera.add_trauma(0.8);
+    camera.add_trauma(0.8);
+    assert_eq!(camera.get_trauma(), 1.0);
+
+    let shaken = camera.advance(unit_view, 1.0);
+    assert!(shaken.x.abs() <= 10.0 && shaken.y.abs() <= 10.0);
+    assert_eq!(camera.get_trauma(), 0.5);
+
+    camera.advance(unit_view, 1.0);
+    assert_eq!(camera.get_trauma(), 0.0);
+
+    // The shake never moves the camera itself
+    assert_eq!(camera.advance(unit_view, 1.0), Vec2::new());
+    assert_eq!(camera.get_position(), Vec2::new());
+}
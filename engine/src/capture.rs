@@ -0,0 +1,182 @@
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use image::{Image, RGBA};
+use Keycode;
+use Error;
+
+// Screenshots and frame sequences saved as PNG files. The engine captures
+// the window at the end of each frame, after the game state has drawn it.
+// There are no hotkeys until the game sets them, see set_screenshot_key.
+pub struct FrameCapture {
+    screenshot_key: Option<Keycode>,
+    record_key: Option<Keycode>,
+    folder: PathBuf,
+    screenshot_requested: bool,
+    recording: Option<Recording>,
+}
+
+struct Recording {
+    folder: PathBuf,
+    frame: u32,
+}
+
+impl Default for FrameCapture {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FrameCapture {
+    pub fn new() -> FrameCapture {
+        FrameCapture {
+            screenshot_key: None,
+            record_key: None,
+            folder: PathBuf::from("screenshots"),
+            screenshot_requested: false,
+            recording: None,
+        }
+    }
+
+    pub fn get_screenshot_key(&self) -> Option<Keycode> {
+        self.screenshot_key
+    }
+
+    // None turns the hotkey off
+    pub fn set_screenshot_key(&mut self, key: Option<Keycode>) {
+        self.screenshot_key = key;
+    }
+
+    pub fn get_record_key(&self) -> Option<Keycode> {
+        self.record_key
+    }
+
+    // Starts and stops recording into a new folder under the screenshot
+    // folder
+    pub fn set_record_key(&mut self, key: Option<Keycode>) {
+        self.record_key = key;
+    }
+
+    pub fn get_folder(&self) -> &str {
+        self.folder.to_str().unwrap_or("")
+    }
+
+    // Where screenshots go, created when the first one is taken
+    pub fn set_folder(&mut self, folder: &str) {
+        self.folder = PathBuf::from(folder);
+    }
+
+    // Saved at the end of the frame
+    pub fn request_screenshot(&mut self) {
+        self.screenshot_requested = true;
+    }
+
+    // Dumps every frame as a numbered PNG into `folder`. This slows the game
+    // down a lot, so frames are better played back at a fixed rate. The PNGs
+    // are not compressed, a 1080p frame takes about 8 MB of disk.
+    pub fn start_recording(&mut self, folder: &str) {
+        self.recording =
+            Some(Recording {
+                folder: PathBuf::from(folder),
+                frame: 0,
+            });
+    }
+
+    pub fn stop_recording(&mut self) {
+        self.recording = None;
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    pub fn on_key_down(&mut self, key: Keycode) {
+        if Some(key) == self.screenshot_key {
+            self.request_screenshot();
+        }
+
+        if Some(key) == self.record_key {
+            if self.is_recording() {
+                println!("Stopped recording");
+                self.stop_recording();
+            } else {
+                let mut folder = self.folder.clone();
+                folder.push(format!("recording-{}", timestamp()));
+                println!("Recording frames to {:?}", folder);
+
+                let folder = folder.to_str().unwrap_or("recording").to_string();
+                self.start_recording(&folder);
+            }
+        }
+    }
+
+    // Whether `end_frame` needs the pixels of this frame
+    pub fn wants_frame(&self) -> bool {
+        self.screenshot_requested || self.recording.is_some()
+    }
+
+    pub fn end_frame(&mut self, frame: &Image<RGBA>) -> Result<(), Error> {
+        if self.screenshot_requested {
+            self.screenshot_requested = false;
+
+            let filename = self.next_screenshot_filename()?;
+            frame.save_png(&filename)?;
+            println!("Saved screenshot to {}", filename);
+        }
+
+        if let Some(ref mut recording) = self.recording {
+            create_folder(&recording.folder)?;
+
+            let mut filename = recording.folder.clone();
+            filename.push(format!("frame-{:05}.png", recording.frame));
+            recording.frame += 1;
+
+            frame.save_png(&path_to_string(&filename)?)?;
+        }
+
+        Ok(())
+    }
+
+    fn next_screenshot_filename(&self) -> Result<String, Error> {
+        create_folder(&self.folder)?;
+
+        let stamp = timestamp();
+        let mut index = 0;
+
+        loop {
+            let mut filename = self.folder.clone();
+            filename.push(
+                if index == 0 {
+                    format!("screenshot-{}.png", stamp)
+                } else {
+                    format!("screenshot-{}-{}.png", stamp, index)
+                }
+            );
+
+            if !filename.exists() {
+                return path_to_string(&filename);
+            }
+
+            index += 1;
+        }
+    }
+}
+
+fn timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+// The PNG writer takes &str, which not every path can be turned into
+fn path_to_string(path: &Path) -> Result<String, Error> {
+    path.to_str()
+        .map(|s| s.to_string())
+        .ok_or_else(|| Error::IO { path: Some(path.to_string_lossy().into_owned()) })
+}
+
+fn create_folder(folder: &Path) -> Result<(), Error> {
+    std::fs::create_dir_all(folder)
+        .map_err(|_| Error::IO { path: folder.to_str().map(|s| s.to_string()) })
+}
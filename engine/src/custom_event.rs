@@ -0,0 +1,134 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+
+use game_object::{EventType, EventReceiver, EventMailbox};
+use scene::SceneObjectId;
+
+// Game specific event payload. Any 'static type can be sent, the game does not
+// have to add a variant to EventType for it.
+#[derive(Clone)]
+pub struct CustomEvent {
+    data: Rc<dyn Any>,
+    type_name: &'static str,
+}
+
+impl CustomEvent {
+    pub fn new<T: Any>(data: T) -> CustomEvent {
+        CustomEvent {
+            data: Rc::new(data),
+            type_name: std::any::type_name::<T>(),
+        }
+    }
+
+    pub fn is<T: Any>(&self) -> bool {
+        self.data.is::<T>()
+    }
+
+    pub fn downcast_ref<T: Any>(&self) -> Option<&T> {
+        self.data.downcast_ref::<T>()
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+}
+
+impl fmt::Debug for CustomEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "CustomEvent({})", self.type_name)
+    }
+}
+
+pub trait TypedEventMailbox {
+    fn submit_typed<T: Any>(&mut self, data: T, receiver: EventReceiver);
+}
+
+impl<M: EventMailbox + ?Sized> TypedEventMailbox for M {
+    fn submit_typed<T: Any>(&mut self, data: T, receiver: EventReceiver) {
+        self.submit_event(EventType::typed(data), receiver);
+    }
+}
+
+type Handler<S> = Box<dyn Fn(&mut S, &CustomEvent, Option<SceneObjectId>)>;
+
+// Typed subscriptions to custom events, for game states and objects that
+// would otherwise downcast every payload by hand.
+pub struct EventHandlers<S> {
+    handlers: HashMap<TypeId, Handler<S>>,
+}
+
+impl<S> Default for EventHandlers<S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S> EventHandlers<S> {
+    pub fn new() -> EventHandlers<S> {
+        EventHandlers {
+            handlers: HashMap::new()
+        }
+    }
+
+    pub fn subscribe<T, F>(mut self, handler: F) -> EventHandlers<S>
+        where T: Any, F: Fn(&mut S, &T, Option<SceneObjectId>) + 'static
+    {
+        self.handlers.insert(
+            TypeId::of::<T>(),
+            Box::new(
+                move |target, event, sender| {
+                    if let Some(data) = event.downcast_ref::<T>() {
+                        handler(target, data, sender);
+                    }
+                }
+            )
+        );
+
+        self
+    }
+
+    // Returns false if nobody subscribed to the event
+    pub fn handle(&self, target: &mut S, event: &EventType, sender: Option<SceneObjectId>) -> bool {
+        if let EventType::Custom { event } = event {
+            if let Some(handler) = self.handlers.get(&(*event.data).type_id()) {
+                handler(target, event, sender);
+                return true;
+            }
+        }
+
+        false
+    }
+}
+
+#[test]
+fn test_typed_events() {
+    use game_object::EventQueue;
+
+    struct Splash { depth: f32 }
+    struct Drip;
+
+    let mut queue = EventQueue::new();
+    queue.submit_typed(Splash { depth: 2.0 }, EventReceiver::Broadcast);
+    queue.submit_typed(Drip, EventReceiver::Scene);
+
+    let splash = queue.poll().unwrap().event_type;
+    let drip = queue.poll().unwrap().event_type;
+
+    assert!(splash.is::<Splash>());
+    assert!(!splash.is::<Drip>());
+    assert_eq!(splash.downcast_ref::<Splash>().map(|s| s.depth), Some(2.0));
+    assert!(drip.downcast_ref::<Splash>().is_none());
+    assert!(splash.name().ends_with("Splash"));
+
+    let handlers =
+        EventHandlers::new()
+            .subscribe(|total: &mut f32, splash: &Splash, _| *total += splash.depth);
+
+    let mut total = 0.0;
+    assert!(handlers.handle(&mut total, &splash, None));
+    assert!(!handlers.handle(&mut total, &drip, None));
+    assert!(!handlers.handle(&mut total, &EventType::Interact, None));
+    assert_eq!(total, 2.0);
+}
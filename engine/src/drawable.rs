@@ -8,6 +8,7 @@ use texture_registry::{Texture, TextureRegistry};
 use vector::Vec2;
 use extent::Extent;
 use rect::Rect2D;
+use font::{Font, TextStyle};
 use sdl2::rect::Point;
 use crate::Color;
 
@@ -34,51 +35,176 @@ impl Origin {
     }
 }
 
+// Per-draw settings on top of the texture and transform. The defaults draw
+// the texture as it is.
+#[derive(Clone, Copy, PartialEq)]
+pub struct DrawParams {
+    pub color: Color,
+    pub alpha: u8,
+    pub flip_horizontal: bool,
+    pub flip_vertical: bool,
+    // Uses the blend mode the texture was loaded with if not set
+    pub blend_mode: Option<BlendMode>,
+}
+
+impl Default for DrawParams {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DrawParams {
+    pub fn new() -> DrawParams {
+        DrawParams {
+            color: Color::RGB(255, 255, 255),
+            alpha: 255,
+            flip_horizontal: false,
+            flip_vertical: false,
+            blend_mode: None,
+        }
+    }
+
+    // Multiplied with the texture colour, white leaves it unchanged
+    pub fn with_color(mut self, color: Color) -> DrawParams {
+        self.color = color;
+        self
+    }
+
+    pub fn with_alpha(mut self, alpha: u8) -> DrawParams {
+        self.alpha = alpha;
+        self
+    }
+
+    pub fn with_flip(mut self, horizontal: bool, vertical: bool) -> DrawParams {
+        self.flip_horizontal = horizontal;
+        self.flip_vertical = vertical;
+        self
+    }
+
+    pub fn with_blend_mode(mut self, blend_mode: BlendMode) -> DrawParams {
+        self.blend_mode = Some(blend_mode);
+        self
+    }
+
+    fn needs_texture_state(&self) -> bool {
+        self.color != Color::RGB(255, 255, 255) || self.alpha != 255 || self.blend_mode.is_some()
+    }
+}
+
 pub trait Drawable {
     fn draw(&self, ctx: &mut DrawContext);
 }
 
-pub struct DrawContext<'t> {
+pub struct DrawContext<'t, 'r> {
     canvas: &'t mut Canvas<Window>,
-    texture_registry: &'t TextureRegistry<'t>,
+    texture_registry: &'t TextureRegistry<'r>,
     camera: &'t Transform,
     screen_bounds: Rect2D,
+    in_viewport: bool,
 }
 
-impl<'t> DrawContext<'t> {
+impl<'t, 'r> DrawContext<'t, 'r> {
     pub fn new(
         canvas: &'t mut Canvas<Window>,
-        texture_registry: &'t TextureRegistry<'t>,
+        texture_registry: &'t TextureRegistry<'r>,
         camera: &'t Transform,
         screen_bounds: Rect2D
     )
-        -> DrawContext<'t>
+        -> DrawContext<'t, 'r>
     {
         DrawContext {
             canvas,
             texture_registry,
             camera,
-            screen_bounds
+            screen_bounds,
+            in_viewport: false
+        }
+    }
+
+    // Draws into part of the canvas only. `viewport` is in pixels, and
+    // everything drawn is relative to it until the context is dropped.
+    pub fn in_viewport(
+        canvas: &'t mut Canvas<Window>,
+        texture_registry: &'t TextureRegistry<'r>,
+        camera: &'t Transform,
+        viewport: Rect2D
+    )
+        -> DrawContext<'t, 'r>
+    {
+        let size = viewport.max - viewport.min;
+
+        canvas.set_viewport(
+            Rect::new(
+                viewport.min.x.round() as i32,
+                viewport.min.y.round() as i32,
+                size.x.round() as u32,
+                size.y.round() as u32
+            )
+        );
+
+        DrawContext {
+            canvas,
+            texture_registry,
+            camera,
+            screen_bounds: Rect2D::new(Vec2::new(), size),
+            in_viewport: true
         }
     }
 
 
     pub fn copy_ex(&mut self, texture: &Texture, src: Rect, dst: Rect, rotation: f64) {
+        self.copy_ex_with(texture, src, dst, rotation, &DrawParams::new());
+    }
+
+    pub fn copy_ex_with(&mut self, texture: &Texture, src: Rect, dst: Rect, rotation: f64, params: &DrawParams) {
+        if !params.needs_texture_state() {
+            self.canvas.copy_ex(
+                &self.texture_registry.get_internal_texture(texture),
+                Some(src),
+                Some(dst),
+                -rotation,
+                None,
+                params.flip_horizontal,
+                params.flip_vertical
+            ).unwrap();
+            return;
+        }
+
+        // Textures are shared between sprites, so the modulation is only set
+        // for this copy
+        let texture_registry = self.texture_registry;
+        let mut internal = texture_registry.get_internal_texture_mut(texture);
+        let previous_blend_mode = internal.blend_mode();
+
+        internal.set_color_mod(params.color.r, params.color.g, params.color.b);
+        internal.set_alpha_mod(params.alpha);
+        if let Some(blend_mode) = params.blend_mode {
+            internal.set_blend_mode(blend_mode);
+        }
+
         self.canvas.copy_ex(
-            self.texture_registry.get_internal_texture(texture),
+            &internal,
             Some(src),
             Some(dst),
             -rotation,
             None,
-            false,
-            false
+            params.flip_horizontal,
+            params.flip_vertical
         ).unwrap();
+
+        internal.set_color_mod(255, 255, 255);
+        internal.set_alpha_mod(255);
+        internal.set_blend_mode(previous_blend_mode);
     }
 
     pub fn draw(&mut self, texture: &Texture, transform: &Transform) {
         self.draw2(texture, transform, Origin::Center);
     }
 
+    pub fn draw_with(&mut self, texture: &Texture, transform: &Transform, params: &DrawParams) {
+        self.draw2_with(texture, transform, Origin::Center, params);
+    }
+
     pub fn draw_rect(&mut self, dst: Rect2D, color: Color) {
         self.canvas.set_blend_mode(BlendMode::Blend);
         let rect =
@@ -116,7 +242,168 @@ impl<'t> DrawContext<'t> {
         self.canvas.fill_rect(rect).unwrap();
     }
 
+    // The primitives below are in world space, thickness included, so they
+    // follow the camera like sprites do
+
+    pub fn draw_line(&mut self, a: Vec2, b: Vec2, thickness: f32, color: Color) {
+        let a = self.world_to_screen(a);
+        let b = self.world_to_screen(b);
+        let thickness = thickness / self.camera.get_scale();
+
+        self.canvas.set_blend_mode(BlendMode::Blend);
+        self.canvas.set_draw_color(color);
+        self.screen_line(a, b, thickness);
+        self.canvas.set_blend_mode(BlendMode::None);
+    }
+
+    pub fn draw_polyline(&mut self, points: &[Vec2], closed: bool, thickness: f32, color: Color) {
+        if points.len() < 2 {
+            return;
+        }
+
+        let points : Vec<Vec2> = points.iter().map(|p| self.world_to_screen(*p)).collect();
+        let thickness = thickness / self.camera.get_scale();
+
+        self.canvas.set_blend_mode(BlendMode::Blend);
+        self.canvas.set_draw_color(color);
+
+        for segment in points.windows(2) {
+            self.screen_line(segment[0], segment[1], thickness);
+        }
+
+        if closed && points.len() > 2 {
+            self.screen_line(points[points.len() - 1], points[0], thickness);
+        }
+
+        self.canvas.set_blend_mode(BlendMode::None);
+    }
+
+    pub fn draw_rect_outline(&mut self, rect: Rect2D, thickness: f32, color: Color) {
+        let corners = [
+            rect.min,
+            Vec2::from_coords(rect.max.x, rect.min.y),
+            rect.max,
+            Vec2::from_coords(rect.min.x, rect.max.y),
+        ];
+
+        self.draw_polyline(&corners, true, thickness, color);
+    }
+
+    pub fn draw_circle(&mut self, center: Vec2, radius: f32, thickness: f32, color: Color) {
+        let points = arc_points(center, radius, 0.0, 2.0 * std::f32::consts::PI, self.camera.get_scale());
+        self.draw_polyline(&points, true, thickness, color);
+    }
+
+    pub fn fill_circle(&mut self, center: Vec2, radius: f32, color: Color) {
+        let points = arc_points(center, radius, 0.0, 2.0 * std::f32::consts::PI, self.camera.get_scale());
+        self.fill_polygon(&points, color);
+    }
+
+    // Angles are in radians, going from `start` to `end`
+    pub fn draw_arc(&mut self, center: Vec2, radius: f32, start: f32, end: f32, thickness: f32, color: Color) {
+        let points = arc_points(center, radius, start, end, self.camera.get_scale());
+        self.draw_polyline(&points, false, thickness, color);
+    }
+
+    pub fn draw_polygon(&mut self, points: &[Vec2], thickness: f32, color: Color) {
+        self.draw_polyline(points, true, thickness, color);
+    }
+
+    // Only convex polygons are filled correctly
+    pub fn fill_polygon(&mut self, points: &[Vec2], color: Color) {
+        if points.len() < 3 {
+            return;
+        }
+
+        let points : Vec<Vec2> = points.iter().map(|p| self.world_to_screen(*p)).collect();
+
+        self.canvas.set_blend_mode(BlendMode::Blend);
+        self.canvas.set_draw_color(color);
+        self.screen_fill_convex(&points);
+        self.canvas.set_blend_mode(BlendMode::None);
+    }
+
+    // Draw colour and blend mode must already be set
+    fn screen_line(&mut self, a: Vec2, b: Vec2, thickness: f32) {
+        let direction = b - a;
+        let length = direction.len();
+
+        if thickness <= 1.5 || length == 0.0 {
+            self.canvas.draw_line(
+                Point::new(a.x.round() as i32, a.y.round() as i32),
+                Point::new(b.x.round() as i32, b.y.round() as i32)
+            ).unwrap();
+            return;
+        }
+
+        let normal = Vec2::from_coords(-direction.y, direction.x) * (thickness * 0.5 / length);
+
+        self.screen_fill_convex(&[a + normal, b + normal, b - normal, a - normal]);
+    }
+
+    fn screen_fill_convex(&mut self, points: &[Vec2]) {
+        for (row, left, right) in convex_spans(points, self.screen_bounds.min.y, self.screen_bounds.max.y) {
+            self.canvas.draw_line(Point::new(left, row), Point::new(right, row)).unwrap();
+        }
+    }
+
+    // World space, laid out from the top of `area`
+    pub fn draw_text(&mut self, font: &Font, text: &str, area: Rect2D, style: &TextStyle) {
+        for (texture, rect) in font.place_glyphs(text, area, style) {
+            let top_left = self.world_to_screen(rect.min);
+            let bottom_right = self.world_to_screen(rect.max);
+            self.copy_with_color(texture, top_left, bottom_right, style.color);
+        }
+    }
+
+    // Same as draw_text, but `area` is in pixels. For scores, menus and
+    // debug info that should stay put when the camera moves.
+    pub fn draw_text_on_screen(&mut self, font: &Font, text: &str, area: Rect2D, style: &TextStyle) {
+        for (texture, rect) in font.place_glyphs(text, area, style) {
+            self.copy_with_color(texture, rect.min, rect.max, style.color);
+        }
+    }
+
+    fn world_to_screen(&self, p: Vec2) -> Vec2 {
+        let mut screen_transform = Transform::new();
+        screen_transform.translate(self.screen_bounds.max * 0.5);
+
+        screen_transform.transform_point(self.camera.transform_point_inv(p))
+    }
+
+    fn copy_with_color(&mut self, texture: &Texture, top_left: Vec2, bottom_right: Vec2, color: Color) {
+        let top_left = top_left.round();
+        let size = bottom_right.round() - top_left;
+
+        let src =
+            Rect::new(
+                texture.offset().x,
+                texture.offset().y,
+                texture.extent().width as u32,
+                texture.extent().height as u32
+            );
+
+        let dst =
+            Rect::new(
+                top_left.x as i32,
+                top_left.y as i32,
+                size.x as u32,
+                size.y as u32
+            );
+
+        let params =
+            DrawParams::new()
+                .with_color(Color::RGB(color.r, color.g, color.b))
+                .with_alpha(color.a);
+
+        self.copy_ex_with(texture, src, dst, 0.0, &params);
+    }
+
     pub fn draw2(&mut self, texture: &Texture, transform: &Transform, origin: Origin) {
+        self.draw2_with(texture, transform, origin, &DrawParams::new());
+    }
+
+    pub fn draw2_with(&mut self, texture: &Texture, transform: &Transform, origin: Origin, params: &DrawParams) {
         let src =
             Rect::new(
                 texture.offset().x,
@@ -167,6 +454,103 @@ impl<'t> DrawContext<'t> {
                 extent.height as u32
             );
 
-        self.copy_ex(texture, src, dst, angle as f64);
+        self.copy_ex_with(texture, src, dst, angle as f64, params);
+    }
+}
+
+impl<'t, 'r> Drop for DrawContext<'t, 'r> {
+    fn drop(&mut self) {
+        if self.in_viewport {
+            self.canvas.set_viewport(None);
+        }
+    }
+}
+
+fn arc_points(center: Vec2, radius: f32, start: f32, end: f32, camera_scale: f32) -> Vec<Vec2> {
+    // Roughly one segment per 6 pixels of the arc on screen
+    let arc_length = (end - start).abs() * radius / camera_scale;
+    let segments = ((arc_length / 6.0).ceil() as usize).clamp(4, 128);
+
+    (0..=segments)
+        .map(
+            |i| {
+                let angle = start + (end - start) * (i as f32 / segments as f32);
+                center + Vec2::from_coords(angle.cos(), angle.sin()) * radius
+            }
+        )
+        .collect()
+}
+
+// Scanline fill of a convex polygon, one horizontal span per pixel row
+// between `top` and `bottom`. Spans are (row, left, right).
+fn convex_spans(points: &[Vec2], top: f32, bottom: f32) -> Vec<(i32, i32, i32)> {
+    let mut min_y = f32::MAX;
+    let mut max_y = f32::MIN;
+
+    for p in points.iter() {
+        min_y = min_y.min(p.y);
+        max_y = max_y.max(p.y);
     }
+
+    let first_row = (min_y.floor() as i32).max(top as i32);
+    let last_row = (max_y.ceil() as i32).min(bottom as i32);
+
+    let mut spans = Vec::new();
+
+    for row in first_row..last_row {
+        let y = row as f32 + 0.5;
+
+        let mut left = f32::MAX;
+        let mut right = f32::MIN;
+
+        for (i, a) in points.iter().enumerate() {
+            let b = points[(i + 1) % points.len()];
+
+            if (a.y <= y && b.y > y) || (b.y <= y && a.y > y) {
+                let x = a.x + (y - a.y) / (b.y - a.y) * (b.x - a.x);
+                left = left.min(x);
+                right = right.max(x);
+            }
+        }
+
+        if left <= right {
+            spans.push((row, left.round() as i32, right.round() as i32));
+        }
+    }
+
+    spans
+}
+
+#[test]
+fn test_fill_spans() {
+    let square = [
+        Vec2::from_coords(10.0, 10.0),
+        Vec2::from_coords(20.0, 10.0),
+        Vec2::from_coords(20.0, 20.0),
+        Vec2::from_coords(10.0, 20.0),
+    ];
+
+    let spans = convex_spans(&square, 0.0, 100.0);
+    assert_eq!(spans.len(), 10);
+    assert!(spans.iter().enumerate().all(|(i, span)| *span == (10 + i as i32, 10, 20)));
+
+    // Rows outside the screen are skipped
+    let spans = convex_spans(&square, 15.0, 18.0);
+    assert_eq!(spans.iter().map(|span| span.0).collect::<Vec<_>>(), vec![15, 16, 17]);
+
+    // A circle of radius 10 covers 20 rows, each span centered on it
+    let circle = arc_points(Vec2::from_coords(50.0, 50.0), 10.0, 0.0, 2.0 * std::f32::consts::PI, 1.0);
+    assert_eq!(circle.len(), 12);
+    assert!((circle[0] - Vec2::from_coords(60.0, 50.0)).len() < 0.001);
+    assert!((circle[11] - circle[0]).len() < 0.001);
+
+    let spans = convex_spans(&circle, 0.0, 100.0);
+    assert_eq!(spans.len(), 20);
+    assert!(spans.iter().all(|&(_, left, right)| left >= 40 && right <= 60 && (left + right - 100).abs() <= 1));
+
+    let widest = spans.iter().map(|&(_, left, right)| right - left).max().unwrap();
+    assert!(widest >= 19);
+
+    // Too few points to cover anything
+    assert!(convex_spans(&square[..2], 0.0, 100.0).is_empty());
 }
@@ -0,0 +1,87 @@
+use std::collections::VecDeque;
+
+use scene::SceneObjectId;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EventConsumer {
+    Object(SceneObjectId),
+    Scene,
+    // Not handled by the scene, returned from Scene::update
+    Parent,
+}
+
+#[derive(Debug, Clone)]
+pub struct EventTraceEntry {
+    pub frame: u64,
+    pub sender: Option<SceneObjectId>,
+    pub receiver: &'static str,
+    pub event: &'static str,
+    pub consumers: Vec<EventConsumer>,
+}
+
+// Records how every dispatched event was routed. Only the most recent
+// entries are kept, so it can be left running while playing.
+pub struct EventTrace {
+    entries: VecDeque<EventTraceEntry>,
+    capacity: usize,
+}
+
+impl EventTrace {
+    pub fn new(capacity: usize) -> EventTrace {
+        EventTrace {
+            entries: VecDeque::new(),
+            capacity: capacity.max(1),
+        }
+    }
+
+    pub fn record(
+        &mut self,
+        frame: u64,
+        sender: Option<SceneObjectId>,
+        receiver: &'static str,
+        event: &'static str,
+        consumers: Vec<EventConsumer>
+    ) {
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+
+        self.entries.push_back(
+            EventTraceEntry {
+                frame,
+                sender,
+                receiver,
+                event,
+                consumers,
+            }
+        );
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item=&EventTraceEntry> {
+        self.entries.iter()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    pub fn dump(&self) {
+        for entry in self.entries.iter() {
+            let consumed_by =
+                if entry.consumers.is_empty() {
+                    "nobody".to_string()
+                } else {
+                    format!("{:?}", entry.consumers)
+                };
+
+            println!(
+                "[frame {}] {} from {:?} to {} consumed by {}",
+                entry.frame,
+                entry.event,
+                entry.sender,
+                entry.receiver,
+                consumed_by
+            );
+        }
+    }
+}
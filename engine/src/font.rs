@@ -0,0 +1,391 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::PathBuf;
+
+use texture_registry::{Texture, TextureRegistry};
+use extent::Extent;
+use offset::Offset;
+use rect::Rect2D;
+use vector::Vec2;
+use Color;
+use Error;
+
+pub struct Glyph {
+    pub texture: Texture,
+    // Where the glyph is drawn relative to the pen position
+    pub offset: Offset,
+    pub advance: i32,
+}
+
+// Bitmap font, either read from an AngelCode BMFont text file (.fnt) or
+// cut out of a texture with all glyphs in a fixed grid.
+pub struct Font {
+    glyphs: HashMap<char, Glyph>,
+    kerning: HashMap<(char, char), i32>,
+    line_height: i32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TextAlign {
+    Left,
+    Center,
+    Right
+}
+
+#[derive(Clone, Copy)]
+pub struct TextStyle {
+    pub align: TextAlign,
+    pub color: Color,
+    pub scale: f32,
+    // Breaks lines between words so they fit the width of the text area
+    pub wrap: bool,
+}
+
+impl Default for TextStyle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TextStyle {
+    pub fn new() -> TextStyle {
+        TextStyle {
+            align: TextAlign::Left,
+            color: Color::RGB(255, 255, 255),
+            scale: 1.0,
+            wrap: true,
+        }
+    }
+
+    pub fn with_align(mut self, align: TextAlign) -> TextStyle {
+        self.align = align;
+        self
+    }
+
+    pub fn with_color(mut self, color: Color) -> TextStyle {
+        self.color = color;
+        self
+    }
+
+    pub fn with_scale(mut self, scale: f32) -> TextStyle {
+        self.scale = scale;
+        self
+    }
+
+    pub fn with_wrap(mut self, wrap: bool) -> TextStyle {
+        self.wrap = wrap;
+        self
+    }
+}
+
+impl Font {
+    pub fn load_fnt(texture_registry: &mut TextureRegistry, filename: &str) -> Result<Font, Error> {
+        let mut data = String::new();
+
+        File::open(filename)
+            .and_then(|mut f| f.read_to_string(&mut data))
+            .map_err(|_| Error::IO { path: Some(filename.to_string()) })?;
+
+        let mut font_folder : PathBuf = filename.into();
+        font_folder.pop();
+
+        let mut pages = HashMap::new();
+        let mut glyphs = HashMap::new();
+        let mut kerning = HashMap::new();
+        let mut line_height = 0;
+
+        for line in data.lines() {
+            let (tag, values) = parse_fnt_line(line);
+
+            let get = |key: &str| -> Result<i32, Error> {
+                values.get(key)
+                    .and_then(|v| v.parse::<i32>().ok())
+                    .ok_or_else(|| Error::FatalError(format!("Missing '{}' in {} line of {}", key, tag, filename)))
+            };
+
+            match tag {
+                "common" => {
+                    line_height = get("lineHeight")?;
+                },
+                "page" => {
+                    let file =
+                        values.get("file")
+                            .ok_or_else(|| Error::FatalError(format!("Page without file in {}", filename)))?;
+
+                    let mut page_filename = font_folder.clone();
+                    page_filename.push(file);
+
+                    let texture = texture_registry.load(page_filename.to_str().unwrap())?;
+                    pages.insert(get("id")?, texture);
+                },
+                "char" => {
+                    let page =
+                        pages.get(&get("page").unwrap_or(0))
+                            .ok_or_else(|| Error::FatalError(format!("Glyph on a missing page in {}", filename)))?;
+
+                    let c =
+                        match std::char::from_u32(get("id")? as u32) {
+                            Some(c) => c,
+                            None => { continue; }
+                        };
+
+                    let glyph =
+                        Glyph {
+                            texture: page.sub_texture(
+                                Offset::from_coords(get("x")?, get("y")?),
+                                Extent::new(get("width")?, get("height")?)
+                            )?,
+                            offset: Offset::from_coords(get("xoffset")?, get("yoffset")?),
+                            advance: get("xadvance")?,
+                        };
+
+                    glyphs.insert(c, glyph);
+                },
+                "kerning" => {
+                    let first = std::char::from_u32(get("first")? as u32);
+                    let second = std::char::from_u32(get("second")? as u32);
+
+                    if let (Some(first), Some(second)) = (first, second) {
+                        kerning.insert((first, second), get("amount")?);
+                    }
+                },
+                _ => { }
+            }
+        }
+
+        Ok(Font { glyphs, kerning, line_height })
+    }
+
+    // Glyphs are read left to right, top to bottom, starting at `first`
+    pub fn from_grid(texture: &Texture, glyph_extent: Extent, first: char) -> Result<Font, Error> {
+        if glyph_extent.width <= 0 || glyph_extent.height <= 0 {
+            return Err(Error::InvalidTileSize);
+        }
+
+        let columns = texture.extent().width / glyph_extent.width;
+        let rows = texture.extent().height / glyph_extent.height;
+
+        let mut glyphs = HashMap::new();
+
+        for i in 0..(columns * rows) {
+            let c =
+                match std::char::from_u32(first as u32 + i as u32) {
+                    Some(c) => c,
+                    None => { break; }
+                };
+
+            let offset =
+                Offset::from_coords(
+                    (i % columns) * glyph_extent.width,
+                    (i / columns) * glyph_extent.height
+                );
+
+            let glyph =
+                Glyph {
+                    texture: texture.sub_texture(offset, glyph_extent)?,
+                    offset: Offset::new(),
+                    advance: glyph_extent.width,
+                };
+
+            glyphs.insert(c, glyph);
+        }
+
+        Ok(Font { glyphs, kerning: HashMap::new(), line_height: glyph_extent.height })
+    }
+
+    pub fn load_grid(
+        texture_registry: &mut TextureRegistry,
+        filename: &str,
+        glyph_extent: Extent,
+        first: char
+    )
+        -> Result<Font, Error>
+    {
+        let texture = texture_registry.load(filename)?;
+        Font::from_grid(&texture, glyph_extent, first)
+    }
+
+    pub fn get_glyph(&self, c: char) -> Option<&Glyph> {
+        self.glyphs.get(&c)
+    }
+
+    pub fn get_kerning(&self, first: char, second: char) -> i32 {
+        self.kerning.get(&(first, second)).cloned().unwrap_or(0)
+    }
+
+    pub fn get_line_height(&self) -> i32 {
+        self.line_height
+    }
+
+    // Width of a single line in font pixels
+    pub fn measure_line(&self, text: &str) -> i32 {
+        let mut width = 0;
+        let mut previous = None;
+
+        for c in text.chars() {
+            if let Some(p) = previous {
+                width += self.get_kerning(p, c);
+            }
+
+            width += self.glyphs.get(&c).map(|g| g.advance).unwrap_or(0);
+            previous = Some(c);
+        }
+
+        width
+    }
+
+    // Splits the text into lines, breaking between words when a line gets
+    // wider than `max_width` font pixels. Words wider than that are kept
+    // whole on their own line.
+    pub fn layout(&self, text: &str, max_width: Option<i32>) -> Vec<String> {
+        let mut lines = Vec::new();
+
+        for paragraph in text.split('\n') {
+            let max_width =
+                match max_width {
+                    Some(max_width) => max_width,
+                    None => {
+                        lines.push(paragraph.to_string());
+                        continue;
+                    }
+                };
+
+            let mut line = String::new();
+
+            for word in paragraph.split(' ') {
+                let candidate =
+                    if line.is_empty() {
+                        word.to_string()
+                    } else {
+                        format!("{} {}", line, word)
+                    };
+
+                if !line.is_empty() && self.measure_line(&candidate) > max_width {
+                    lines.push(line);
+                    line = word.to_string();
+                } else {
+                    line = candidate;
+                }
+            }
+
+            lines.push(line);
+        }
+
+        lines
+    }
+
+    // Where every glyph goes when the text is laid out from the top of
+    // `area`. Lines that do not fit are left out when wrapping.
+    pub fn place_glyphs(&self, text: &str, area: Rect2D, style: &TextStyle) -> Vec<(&Texture, Rect2D)> {
+        let scale = style.scale;
+        let line_height = self.line_height as f32 * scale;
+
+        let max_width =
+            if style.wrap {
+                Some((area.width() / scale).floor() as i32)
+            } else {
+                None
+            };
+
+        let mut result = Vec::new();
+
+        for (i, line) in self.layout(text, max_width).iter().enumerate() {
+            let top = area.min.y + i as f32 * line_height;
+
+            if style.wrap && top + line_height > area.max.y {
+                break;
+            }
+
+            let width = self.measure_line(line) as f32 * scale;
+
+            let mut x =
+                match style.align {
+                    TextAlign::Left => area.min.x,
+                    TextAlign::Center => area.center().x - width * 0.5,
+                    TextAlign::Right => area.max.x - width,
+                };
+
+            let mut previous = None;
+
+            for c in line.chars() {
+                if let Some(p) = previous {
+                    x += self.get_kerning(p, c) as f32 * scale;
+                }
+
+                if let Some(glyph) = self.glyphs.get(&c) {
+                    let extent = glyph.texture.extent();
+
+                    if extent.width > 0 && extent.height > 0 {
+                        let top_left =
+                            Vec2::from_coords(
+                                x + glyph.offset.x as f32 * scale,
+                                top + glyph.offset.y as f32 * scale
+                            );
+
+                        result.push((&glyph.texture, Rect2D::new(top_left, top_left + extent.to_vec() * scale)));
+                    }
+
+                    x += glyph.advance as f32 * scale;
+                }
+
+                previous = Some(c);
+            }
+        }
+
+        result
+    }
+}
+
+// A line is a tag followed by key=value pairs, where values with spaces in
+// them are quoted
+fn parse_fnt_line(line: &str) -> (&str, HashMap<String, String>) {
+    let line = line.trim();
+    let (tag, mut rest) =
+        match line.find(' ') {
+            Some(index) => (&line[..index], &line[index..]),
+            None => (line, "")
+        };
+
+    let mut values = HashMap::new();
+
+    loop {
+        rest = rest.trim_start();
+
+        let key_end =
+            match rest.find('=') {
+                Some(index) => index,
+                None => { break; }
+            };
+
+        let key = rest[..key_end].trim().to_string();
+        rest = &rest[key_end + 1..];
+
+        let value =
+            if let Some(quoted) = rest.strip_prefix('"') {
+                let end = quoted.find('"').unwrap_or(quoted.len());
+                let value = &quoted[..end];
+                rest = quoted.get(end + 1..).unwrap_or("");
+                value
+            } else {
+                let end = rest.find(' ').unwrap_or(rest.len());
+                let value = &rest[..end];
+                rest = &rest[end..];
+                value
+            };
+
+        values.insert(key, value.to_string());
+    }
+
+    (tag, values)
+}
+
+#[test]
+fn test_parse_fnt_line_with_quoted_value() {
+    let (tag, values) = parse_fnt_line("info face=\"Press Start\" size=16 bold=0");
+
+    assert_eq!(tag, "info");
+    assert_eq!(values.get("face").map(|v| v.as_str()), Some("Press Start"));
+    assert_eq!(values.get("size").map(|v| v.as_str()), Some("16"));
+    assert_eq!(values.get("bold").map(|v| v.as_str()), Some("0"));
+}
@@ -3,9 +3,13 @@ use drawable::DrawContext;
 use transform::Transform;
 use vector::Vec2;
 use scene::SceneObjectId;
+use custom_event::CustomEvent;
+use scene_index::SceneIndex;
+use as_any::AsAny;
 use Engine;
 use rect::Rect2D;
 use std::rc::Rc;
+use std::collections::VecDeque;
 
 #[derive(Hash, Eq, PartialEq, Clone, Copy, Debug)]
 pub enum Items {
@@ -32,15 +36,45 @@ pub enum EventType {
     SendItem { item: Item },
     Suck,
     DeleteMe,
-    FreeFromDust,
-    PlankBroke,
-    PlankRepaired,
-    OceanRiseRate { rate: f32 },
-    BoatSunk,
-    Custom { data: Rc<dyn Any> }
+    Custom { event: CustomEvent }
 }
 
-#[derive(Debug)]
+impl EventType {
+    pub fn typed<T: Any>(data: T) -> EventType {
+        EventType::Custom { event: CustomEvent::new(data) }
+    }
+
+    pub fn is<T: Any>(&self) -> bool {
+        self.downcast_ref::<T>().is_some()
+    }
+
+    pub fn downcast_ref<T: Any>(&self) -> Option<&T> {
+        match *self {
+            EventType::Custom { ref event } => event.downcast_ref::<T>(),
+            _ => None
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            EventType::Interact => "Interact",
+            EventType::Collide { .. } => "Collide",
+            EventType::Probe { .. } => "Probe",
+            EventType::ProbeReply { .. } => "ProbeReply",
+            EventType::RayCast { .. } => "RayCast",
+            EventType::RayCastReply { .. } => "RayCastReply",
+            EventType::Attack { .. } => "Attack",
+            EventType::Loot { .. } => "Loot",
+            EventType::RequestItem { .. } => "RequestItem",
+            EventType::SendItem { .. } => "SendItem",
+            EventType::Suck => "Suck",
+            EventType::DeleteMe => "DeleteMe",
+            EventType::Custom { ref event } => event.type_name(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
 pub enum EventReceiver {
     // Only the nearest object to the origin receives the event
     Nearest { origin: Vec2, max_distance: Option<f32> },
@@ -54,20 +88,78 @@ pub enum EventReceiver {
     Nearby { origin: Vec2, max_distance: Option<f32> },
 }
 
+impl EventReceiver {
+    pub fn kind(&self) -> &'static str {
+        match *self {
+            EventReceiver::Nearest { .. } => "Nearest",
+            EventReceiver::Broadcast => "Broadcast",
+            EventReceiver::Addressed { .. } => "Addressed",
+            EventReceiver::Scene => "Scene",
+            EventReceiver::Nearby { .. } => "Nearby",
+        }
+    }
+}
+
+// Higher priority events are dispatched first. Events with the same priority
+// are dispatched in the order they were submitted.
+#[derive(Hash, Eq, PartialEq, Ord, PartialOrd, Clone, Copy, Debug)]
+pub enum EventPriority {
+    Low,
+    Normal,
+    High,
+}
+
 #[derive(Debug)]
 pub struct GameEvent {
     pub event_type: EventType,
     pub receiver: EventReceiver,
     pub sender: Option<SceneObjectId>,
+    pub priority: EventPriority,
+}
 
+// Changes to the scene itself, applied once the scene is done updating
+// objects and dispatching events for the frame
+pub enum SceneCommand {
+    SpawnObject { object: Box<dyn GameObject> },
+    ScheduleEvent { delay: f32, event: EventType, receiver: EventReceiver },
+    // Cancels every event the sending object scheduled
+    CancelScheduledEvents,
 }
 
 pub trait EventMailbox  {
-    fn submit_event(&mut self, event: EventType, receiver: EventReceiver);
+    fn submit_event(&mut self, event: EventType, receiver: EventReceiver) {
+        self.submit_event_with_priority(event, receiver, EventPriority::Normal);
+    }
+
+    fn submit_event_with_priority(&mut self, event: EventType, receiver: EventReceiver, priority: EventPriority);
+
+    fn submit_command(&mut self, command: SceneCommand);
+
+    // What the scene looked like when this update started, if the mailbox
+    // belongs to a scene
+    fn get_scene_index(&self) -> Option<&SceneIndex> { None }
+
+    // The object being updated, so it can address events to itself
+    fn get_sender_id(&self) -> Option<SceneObjectId> { None }
+}
+
+impl<'a> dyn EventMailbox + 'a {
+    pub fn spawn_object<T: GameObject>(&mut self, object: T) {
+        self.submit_command(SceneCommand::SpawnObject { object: Box::new(object) });
+    }
+
+    pub fn schedule_event(&mut self, delay: f32, event: EventType, receiver: EventReceiver) {
+        self.submit_command(SceneCommand::ScheduleEvent { delay, event, receiver });
+    }
+
+    pub fn cancel_scheduled_events(&mut self) {
+        self.submit_command(SceneCommand::CancelScheduledEvents);
+    }
 }
 
 pub struct EventQueue {
-    queue: Vec<GameEvent>
+    queues: [VecDeque<GameEvent>; 3],
+    commands: VecDeque<(SceneCommand, Option<SceneObjectId>)>
 }
 
 impl Default for EventQueue {
@@ -79,47 +171,91 @@ impl Default for EventQueue {
 impl EventQueue {
     pub fn new() -> EventQueue {
         EventQueue {
-            queue: Vec::new()
+            queues: [VecDeque::new(), VecDeque::new(), VecDeque::new()],
+            commands: VecDeque::new()
         }
     }
 
     pub fn poll(&mut self) -> Option<GameEvent> {
-        self.queue.pop()
+        self.queues.iter_mut().rev().find_map(|queue| queue.pop_front())
+    }
+
+    pub fn len(&self) -> usize {
+        self.queues.iter().map(|queue| queue.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn push(&mut self, event: GameEvent) {
+        self.queues[event.priority as usize].push_back(event);
+    }
+
+    pub fn poll_command(&mut self) -> Option<(SceneCommand, Option<SceneObjectId>)> {
+        self.commands.pop_front()
     }
 
     pub fn bind_to_sender(&mut self, sender_id: SceneObjectId) -> SenderBoundEventQueue {
         SenderBoundEventQueue {
             event_queue: self,
-            sender_id
+            sender_id,
+            scene_index: None
         }
     }
 }
 
 impl EventMailbox for EventQueue {
-    fn submit_event(&mut self, event: EventType, receiver: EventReceiver) {
+    fn submit_event_with_priority(&mut self, event: EventType, receiver: EventReceiver, priority: EventPriority) {
         let game_event = GameEvent {
             event_type: event,
             receiver,
-            sender: None
+            sender: None,
+            priority
         };
-        self.queue.push(game_event);
+        self.push(game_event);
+    }
+
+    fn submit_command(&mut self, command: SceneCommand) {
+        self.commands.push_back((command, None));
     }
 }
 
 pub struct SenderBoundEventQueue<'t> {
     event_queue: &'t mut EventQueue,
-    sender_id: SceneObjectId
+    sender_id: SceneObjectId,
+    scene_index: Option<&'t SceneIndex>
+}
+
+impl<'t> SenderBoundEventQueue<'t> {
+    pub fn with_scene_index(mut self, scene_index: &'t SceneIndex) -> SenderBoundEventQueue<'t> {
+        self.scene_index = Some(scene_index);
+        self
+    }
 }
 
 impl<'t> EventMailbox for SenderBoundEventQueue<'t> {
-    fn submit_event(&mut self, event: EventType, receiver: EventReceiver) {
+    fn submit_event_with_priority(&mut self, event: EventType, receiver: EventReceiver, priority: EventPriority) {
         let game_event = GameEvent {
             event_type: event,
             receiver,
-            sender: Some(self.sender_id)
+            sender: Some(self.sender_id),
+            priority
         };
 
-        self.event_queue.queue.push(game_event);
+        self.event_queue.push(game_event);
+    }
+
+    fn submit_command(&mut self, command: SceneCommand) {
+        self.event_queue.commands.push_back((command, Some(self.sender_id)));
+    }
+
+    fn get_scene_index(&self) -> Option<&SceneIndex> {
+        self.scene_index
+    }
+
+    fn get_sender_id(&self) -> Option<SceneObjectId> {
+        Some(self.sender_id)
     }
 }
 
@@ -355,7 +491,7 @@ pub trait Rotatable {
     fn get_inv_inertia(&self) -> f32;
 }
 
-pub trait GameObject: 'static {
+pub trait GameObject: AsAny + 'static {
     fn update(&mut self, ctx: &mut Engine, event_mailbox: &mut dyn EventMailbox, dt: f32) -> bool;
 
     fn render(&self, ctx: &mut DrawContext);
@@ -364,6 +500,19 @@ pub trait GameObject: 'static {
 
     fn get_physical_object_mut(&mut self) -> Option<&mut dyn PhysicalObject> { None }
 
+    // The transform the scene moves when the object is part of a hierarchy.
+    // Objects without physics can still be parents or children by returning
+    // their own transform here.
+    fn get_scene_transform(&self) -> Option<&Transform> {
+        self.get_physical_object().map(|po| po.get_transform())
+    }
+
+    fn get_scene_transform_mut(&mut self) -> Option<&mut Transform> {
+        self.get_physical_object_mut().map(|po| po.get_transform_mut())
+    }
+
     fn on_event(&mut self, _event: EventType, _sender: Option<SceneObjectId>) -> bool { false }
     fn get_z_index(&self) -> i32 { 0 }
+
+    fn get_tags(&self) -> &[&'static str] { &[] }
 }
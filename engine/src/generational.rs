@@ -0,0 +1,131 @@
+// Storage that hands out stable handles. Removing an entry never moves the
+// others, and a handle to a removed entry will not match whatever gets stored
+// in the same slot later on.
+
+#[derive(Hash, Eq, PartialEq, Ord, PartialOrd, Clone, Copy, Debug)]
+pub struct GenerationalIndex {
+    index: usize,
+    generation: u32,
+}
+
+impl GenerationalIndex {
+    pub fn index(&self) -> usize { self.index }
+    pub fn generation(&self) -> u32 { self.generation }
+}
+
+struct Entry<T> {
+    generation: u32,
+    value: Option<T>,
+}
+
+pub struct GenerationalArena<T> {
+    entries: Vec<Entry<T>>,
+    free_list: Vec<usize>,
+    len: usize,
+}
+
+impl<T> Default for GenerationalArena<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> GenerationalArena<T> {
+    pub fn new() -> GenerationalArena<T> {
+        GenerationalArena {
+            entries: Vec::new(),
+            free_list: Vec::new(),
+            len: 0,
+        }
+    }
+
+    pub fn insert(&mut self, value: T) -> GenerationalIndex {
+        self.len += 1;
+
+        if let Some(index) = self.free_list.pop() {
+            let entry = &mut self.entries[index];
+            entry.generation += 1;
+            entry.value = Some(value);
+
+            GenerationalIndex { index, generation: entry.generation }
+        } else {
+            self.entries.push(Entry { generation: 0, value: Some(value) });
+
+            GenerationalIndex { index: self.entries.len() - 1, generation: 0 }
+        }
+    }
+
+    pub fn remove(&mut self, id: GenerationalIndex) -> Option<T> {
+        if !self.contains(id) {
+            return None;
+        }
+
+        self.len -= 1;
+        self.free_list.push(id.index);
+        self.entries[id.index].value.take()
+    }
+
+    pub fn contains(&self, id: GenerationalIndex) -> bool {
+        self.get(id).is_some()
+    }
+
+    pub fn get(&self, id: GenerationalIndex) -> Option<&T> {
+        match self.entries.get(id.index) {
+            Some(entry) if entry.generation == id.generation => entry.value.as_ref(),
+            _ => None
+        }
+    }
+
+    pub fn get_mut(&mut self, id: GenerationalIndex) -> Option<&mut T> {
+        match self.entries.get_mut(id.index) {
+            Some(entry) if entry.generation == id.generation => entry.value.as_mut(),
+            _ => None
+        }
+    }
+
+    pub fn len(&self) -> usize { self.len }
+
+    pub fn is_empty(&self) -> bool { self.len == 0 }
+
+    pub fn ids(&self) -> Vec<GenerationalIndex> {
+        self.iter().map(|(id, _)| id).collect()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item=(GenerationalIndex, &T)> {
+        self.entries.iter().enumerate().filter_map(
+            |(index, entry)| {
+                let generation = entry.generation;
+                entry.value.as_ref().map(|value| (GenerationalIndex { index, generation }, value))
+            }
+        )
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item=(GenerationalIndex, &mut T)> {
+        self.entries.iter_mut().enumerate().filter_map(
+            |(index, entry)| {
+                let generation = entry.generation;
+                entry.value.as_mut().map(|value| (GenerationalIndex { index, generation }, value))
+            }
+        )
+    }
+}
+
+#[test]
+fn test_removed_handle_does_not_alias() {
+    let mut arena = GenerationalArena::new();
+
+    let a = arena.insert("a");
+    let b = arena.insert("b");
+    let c = arena.insert("c");
+
+    assert_eq!(arena.remove(a), Some("a"));
+    assert_eq!(arena.get(b), Some(&"b"));
+    assert_eq!(arena.get(c), Some(&"c"));
+
+    let d = arena.insert("d");
+    assert_eq!(d.index(), a.index());
+    assert_eq!(arena.get(a), None);
+    assert_eq!(arena.remove(a), None);
+    assert_eq!(arena.get(d), Some(&"d"));
+    assert_eq!(arena.len(), 3);
+}
@@ -12,6 +12,7 @@ use game_object::{
     CollisionShape
 };
 use rect::Rect2D;
+use image::{Image, RGBA};
 use std::fs::File;
 use std::io::Read;
 use std::io::Write;
@@ -234,6 +235,22 @@ impl Grid2 {
     }
 
     pub fn get_tile_type_count(&self) -> u32 { self.tile_list.len() as u32 }
+
+    // One pixel per tile, e.g. for a minimap through
+    // TextureRegistry::create_from_image
+    pub fn to_image<F>(&self, tile_color: F) -> Image<RGBA>
+        where F: Fn(TileIndex) -> RGBA
+    {
+        Image::from_fn(self.width, self.height, |x, y| tile_color(self.buffer[(y * self.width + x) as usize]))
+    }
+
+    pub fn get_bounds(&self) -> Rect2D {
+        let tile_size = self.tile_size as f32;
+        Rect2D::new(
+            Vec2::new(),
+            Vec2::from_coords(self.width as f32 * tile_size, self.height as f32 * tile_size)
+        )
+    }
 }
 
 impl LevelCollider for Grid2 {
@@ -295,3 +312,22 @@ impl<'t> Drawable for GridWithInterleavedScene<'t> {
         self.grid.draw_with_interleaved_scene(ctx, Some(self.scene));
     }
 }
+
+#[test]
+fn test_grid_to_image() {
+    let mut grid = Grid2::new(3, 2, 10);
+    grid.set_tile_at(Vec2::from_coords(25.0, 15.0), 1).unwrap();
+
+    let image = grid.to_image(|tile| {
+        if tile == 0 {
+            RGBA { r: 0, g: 0, b: 0, a: 0 }
+        } else {
+            RGBA { r: 255, g: 255, b: 255, a: 255 }
+        }
+    });
+
+    assert_eq!(image.width(), 3);
+    assert_eq!(image.height(), 2);
+    assert_eq!(image.get_pixel(2, 1).map(|p| p.a), Some(255));
+    assert_eq!(image.get_pixel(0, 0).map(|p| p.a), Some(0));
+}
@@ -0,0 +1,156 @@
+use std::collections::HashMap;
+use std::fs;
+use std::time::SystemTime;
+
+// Watches asset files during development by polling their modification
+// times. Nothing is watched until it is enabled, see Engine::enable_hot_reload.
+pub struct HotReload {
+    enabled: bool,
+    interval: f32,
+    elapsed: f32,
+    files: HashMap<String, Option<SystemTime>>,
+    changed: Vec<String>,
+}
+
+impl Default for HotReload {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HotReload {
+    pub fn new() -> HotReload {
+        HotReload {
+            enabled: false,
+            interval: 0.5,
+            elapsed: 0.0,
+            files: HashMap::new(),
+            changed: Vec::new(),
+        }
+    }
+
+    // Seconds between two polls
+    pub fn with_interval(mut self, interval: f32) -> HotReload {
+        self.interval = interval;
+        self
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    pub fn get_interval(&self) -> f32 {
+        self.interval
+    }
+
+    pub fn set_interval(&mut self, interval: f32) {
+        self.interval = interval;
+    }
+
+    // Watching a file again keeps the time it was last seen with
+    pub fn watch(&mut self, path: &str) {
+        if !self.files.contains_key(path) {
+            self.files.insert(path.to_string(), modified_time(path));
+        }
+    }
+
+    pub fn unwatch(&mut self, path: &str) {
+        self.files.remove(path);
+    }
+
+    pub fn is_watched(&self, path: &str) -> bool {
+        self.files.contains_key(path)
+    }
+
+    // Files that changed since the previous poll. Filled for one frame only.
+    pub fn get_changed(&self) -> &[String] {
+        &self.changed
+    }
+
+    pub fn has_changed(&self, path: &str) -> bool {
+        self.changed.iter().any(|p| p == path)
+    }
+
+    pub fn has_any_changed(&self, paths: &[String]) -> bool {
+        paths.iter().any(|path| self.has_changed(path))
+    }
+
+    // Returns true if it polled this time. Files that disappear for a while,
+    // e.g. while an editor saves them, count as changed once they are back.
+    pub fn update(&mut self, dt: f32) -> bool {
+        self.changed.clear();
+
+        if !self.enabled {
+            return false;
+        }
+
+        self.elapsed += dt;
+        if self.elapsed < self.interval {
+            return false;
+        }
+        self.elapsed = 0.0;
+
+        for (path, last_modified) in self.files.iter_mut() {
+            let modified = modified_time(path);
+
+            if modified.is_some() && modified != *last_modified {
+                self.changed.push(path.clone());
+            }
+
+            if modified.is_some() {
+                *last_modified = modified;
+            }
+        }
+
+        true
+    }
+}
+
+fn modified_time(path: &str) -> Option<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+#[test]
+fn test_touched_file_is_changed() {
+    use std::fs::File;
+    use std::io::Write;
+    use std::time::Duration;
+
+    let mut path = std::env::temp_dir();
+    path.push(format!("hot_reload_test_{}.json", std::process::id()));
+    let path_string = path.to_str().unwrap().to_string();
+
+    File::create(&path).unwrap().write_all(b"{}").unwrap();
+
+    let mut hot_reload = HotReload::new().with_interval(0.5);
+    hot_reload.set_enabled(true);
+    hot_reload.watch(&path_string);
+
+    let watched = vec![path_string.clone()];
+
+    assert!(hot_reload.update(1.0));
+    assert!(!hot_reload.has_any_changed(&watched));
+
+    // Touch the file later than it was written, file times can be coarse
+    let later = modified_time(&path_string).unwrap() + Duration::from_secs(2);
+    File::options().write(true).open(&path).unwrap().set_modified(later).unwrap();
+
+    // Not polled before the interval is up
+    assert!(!hot_reload.update(0.1));
+    assert!(!hot_reload.has_any_changed(&watched));
+
+    assert!(hot_reload.update(0.5));
+    assert!(hot_reload.has_any_changed(&watched));
+
+    // Only reported for one update
+    hot_reload.update(0.5);
+    assert!(!hot_reload.has_any_changed(&watched));
+
+    std::fs::remove_file(&path).unwrap();
+}
@@ -3,7 +3,10 @@ use serde_json;
 use std::collections::HashMap;
 
 use Engine;
+use Error;
 use grid2::Grid2;
+use texture_registry::Texture;
+use texture_atlas::TextureAtlas;
 
 use std::fs::File;
 use std::io::Read;
@@ -24,7 +27,9 @@ pub struct LevelInfo {
     height: i32,
     ground: LayerInfo,
     objects: LayerInfo,
-    special_blocks: HashMap<String, u32>
+    special_blocks: HashMap<String, u32>,
+    #[serde(default)]
+    lightmap: Option<String>
 }
 
 
@@ -33,11 +38,26 @@ pub struct Level {
     ground_filename: String,
     pub objects: Grid2,
     objects_filename: String,
-    pub special_blocks: HashMap<String, u32>
+    pub special_blocks: HashMap<String, u32>,
+    // Baked lighting for the whole level, see Lighting::set_lightmap
+    pub lightmap: Option<Texture>,
+    filename: String,
+    grid_size: u32,
+    source_files: Vec<String>
+}
+
+fn tile_filenames(image_folder: &Path, layer_info: &LayerInfo) -> Vec<String> {
+    layer_info.tiles.iter()
+        .map(|tile| {
+            let mut tile_filename = image_folder.to_path_buf();
+            tile_filename.push(tile);
+            tile_filename.to_str().unwrap().to_string()
+        })
+        .collect()
 }
 
 fn load_layer(
-    ctx: &mut Engine,
+    atlas: &TextureAtlas,
     levels_folder: &Path,
     image_folder: &Path,
     layer_info: &LayerInfo,
@@ -54,14 +74,9 @@ fn load_layer(
             Grid2::new(32, 18, grid_size)
         };
 
-    for tile in layer_info.tiles.iter() {
-        let mut tile_filename = image_folder.to_path_buf();
-        tile_filename.push(tile);
-        println!("Loading tile texture: {:?}", tile_filename);
-
-        let texture = ctx.get_texture_registry().load(tile_filename.to_str().unwrap()).unwrap();
-
-        grid.add_tile_type(texture);
+    for tile_filename in tile_filenames(image_folder, layer_info) {
+        let texture = atlas.get(&tile_filename).unwrap();
+        grid.add_tile_type(texture.clone());
     }
 
     (grid, filename.to_str().unwrap().to_string())
@@ -73,11 +88,23 @@ impl Level {
         filename: &str,
         grid_size: u32,
     ) -> Level {
-        let mut file = File::open(filename).unwrap();
+        Level::try_load_from_file(ctx, filename, grid_size).unwrap()
+    }
+
+    pub fn try_load_from_file(
+        ctx: &mut Engine,
+        filename: &str,
+        grid_size: u32,
+    ) -> Result<Level, Error> {
         let mut data = String::new();
-        file.read_to_string(&mut data).unwrap();
 
-        let level_info = serde_json::from_str::<LevelInfo>(&data).unwrap();
+        File::open(filename)
+            .and_then(|mut f| f.read_to_string(&mut data))
+            .map_err(|_| Error::IO { path: Some(filename.to_string()) })?;
+
+        let level_info =
+            serde_json::from_str::<LevelInfo>(&data)
+                .map_err(|e| Error::FatalError(format!("Could not parse {}: {}", filename, e)))?;
 
         let mut level_folder : PathBuf = filename.into();
         level_folder.pop();
@@ -87,18 +114,66 @@ impl Level {
         image_folder.push("images");
 
 
+        // All tiles go into a few large textures instead of one each
+        let mut tiles = tile_filenames(&image_folder, &level_info.ground);
+        tiles.extend(tile_filenames(&image_folder, &level_info.objects));
+        println!("Packing {} tile textures", tiles.len());
+
+        let atlas = TextureAtlas::from_files(ctx.get_texture_registry(), &tiles)?;
+
         let (ground, ground_filename) =
-            load_layer(ctx, &level_folder, &image_folder, &level_info.ground, grid_size);
+            load_layer(&atlas, &level_folder, &image_folder, &level_info.ground, grid_size);
         let (objects, objects_filename) =
-            load_layer(ctx, &level_folder, &image_folder, &level_info.objects, grid_size);
+            load_layer(&atlas, &level_folder, &image_folder, &level_info.objects, grid_size);
+
+        let mut source_files = vec![filename.to_string(), ground_filename.clone(), objects_filename.clone()];
+        source_files.extend(tiles);
+        ctx.watch_assets(&source_files);
+
+        let lightmap =
+            level_info.lightmap.as_ref().and_then(|file| {
+                let mut lightmap_filename = level_folder.clone();
+                lightmap_filename.push(file);
+
+                match ctx.get_texture_registry().load(lightmap_filename.to_str().unwrap()) {
+                    Ok(texture) => Some(texture),
+                    Err(_) => {
+                        println!("Unable to open lightmap {}", lightmap_filename.to_str().unwrap());
+                        None
+                    }
+                }
+            });
+
+        let level =
+            Level {
+                ground,
+                ground_filename,
+                objects,
+                objects_filename,
+                special_blocks: level_info.special_blocks,
+                lightmap,
+                filename: filename.to_string(),
+                grid_size,
+                source_files
+            };
+
+        Ok(level)
+    }
 
+    // The JSON file, both grids and the tile images, e.g. for
+    // Engine::assets_changed
+    pub fn get_source_files(&self) -> &[String] {
+        &self.source_files
+    }
 
-        Level {
-            ground,
-            ground_filename,
-            objects,
-            objects_filename,
-            special_blocks: level_info.special_blocks
+    // Reads everything from disk again. Changes made to the grids since
+    // loading are lost, so save first if they should stay. Files may be
+    // half written when this runs, so on errors the old level stays.
+    pub fn reload(&mut self, ctx: &mut Engine) {
+        println!("Reloading level {}", self.filename);
+        match Level::try_load_from_file(ctx, &self.filename, self.grid_size) {
+            Ok(level) => *self = level,
+            Err(e) => println!("Could not reload {}: {:?}", self.filename, e),
         }
     }
 
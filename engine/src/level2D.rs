@@ -1,5 +1,6 @@
 use std::collections::HashMap;
 use texture_registry::Texture;
+use texture_atlas::TextureAtlas;
 use serde_json;
 use drawable::{Drawable, DrawContext};
 
@@ -46,7 +47,8 @@ pub struct Level2D {
     pub save_filename: String,
     pub object_textures: HashMap<String, Texture>,
     pub layer_max: u32,
-    pub layers_to_draw: Vec<u32>
+    pub layers_to_draw: Vec<u32>,
+    source_files: Vec<String>
 }
 
 impl Drawable for Level2D {
@@ -71,12 +73,19 @@ impl Drawable for Level2D {
 
 impl Level2D {
     pub fn load_from_file(ctx: &mut Engine, filename: &str) -> Level2D {
-        let mut file = File::open(filename).unwrap();
+        Level2D::try_load_from_file(ctx, filename).unwrap()
+    }
 
+    pub fn try_load_from_file(ctx: &mut Engine, filename: &str) -> Result<Level2D, Error> {
         let mut data = String::new();
-        file.read_to_string(&mut data).unwrap();
 
-        let level_instance = serde_json::from_str::<LevelInstance>(&data).unwrap();
+        File::open(filename)
+            .and_then(|mut f| f.read_to_string(&mut data))
+            .map_err(|_| Error::IO { path: Some(filename.to_string()) })?;
+
+        let level_instance =
+            serde_json::from_str::<LevelInstance>(&data)
+                .map_err(|e| Error::FatalError(format!("Could not parse {}: {}", filename, e)))?;
 
         let mut level_folder : PathBuf = filename.into();
         level_folder.pop();
@@ -91,18 +100,30 @@ impl Level2D {
 
         let mut layer_max: u32 = 0;
 
+        let object_filename = |object: &ObjectType| {
+            let mut object_filename = image_folder.clone();
+            object_filename.push(object.file.clone());
+            object_filename.to_str().unwrap().to_string()
+        };
+
+        let filenames : Vec<String> = level_instance.object_types.iter().map(&object_filename).collect();
+        println!("Packing {} object textures", filenames.len());
+
+        let atlas = TextureAtlas::from_files(ctx.get_texture_registry(), &filenames)?;
+
+        let mut source_files = vec![filename.to_string()];
+        source_files.extend(filenames);
+        ctx.watch_assets(&source_files);
+
         for object in level_instance.object_types.iter() {
             let curr_max = object.layers.iter().max();
             if let Some(i) = curr_max {
                 layer_max = layer_max.max(*i);
             };
-            let mut object_filename = image_folder.clone();
-            object_filename.push(object.file.clone());
-            println!("Loading object texture: {:?}", object_filename);
 
-            let texture = ctx.get_texture_registry().load(object_filename.to_str().unwrap()).unwrap();
+            let texture = atlas.get(&object_filename(object)).unwrap();
 
-            object_textures.insert(object.file.clone(), texture);
+            object_textures.insert(object.file.clone(), texture.clone());
         }
         println!("Layer max is {}", layer_max);
         let mut layers_to_draw: Vec<u32> = Vec::new();
@@ -110,12 +131,35 @@ impl Level2D {
             layers_to_draw.push(i);
         }
 
-        Level2D {
-            level_instance,
-            save_filename,
-            object_textures,
-            layer_max,
-            layers_to_draw
+        let level =
+            Level2D {
+                level_instance,
+                save_filename,
+                object_textures,
+                layer_max,
+                layers_to_draw,
+                source_files
+            };
+
+        Ok(level)
+    }
+
+    // The JSON file and the object images
+    pub fn get_source_files(&self) -> &[String] {
+        &self.source_files
+    }
+
+    // Reads the level from disk again, keeping the layers that are drawn.
+    // On errors the old level stays, see Level::reload.
+    pub fn reload(&mut self, ctx: &mut Engine) {
+        println!("Reloading level {}", self.save_filename);
+        match Level2D::try_load_from_file(ctx, &self.save_filename) {
+            Ok(level) => {
+                let layers_to_draw = self.layers_to_draw.clone();
+                *self = level;
+                self.layers_to_draw = layers_to_draw;
+            },
+            Err(e) => println!("Could not reload {}: {:?}", self.save_filename, e),
         }
     }
 
@@ -142,6 +186,23 @@ impl Level2D {
         }
     }
 
+    // Area covered by all object instances, ignoring their rotation
+    pub fn get_bounds(&self) -> Rect2D {
+        let mut bounds = Rect2D::empty();
+
+        for instance in self.level_instance.object_instances.iter() {
+            let object_type = self.level_instance.object_types.get(instance.object_id as usize).unwrap();
+            let texture = self.object_textures.get(&object_type.file).unwrap();
+
+            let half_size = Vec2::from_coords(texture.extent().width as f32, texture.extent().height as f32) * 0.5 * instance.scale;
+
+            bounds.expand(instance.position - half_size);
+            bounds.expand(instance.position + half_size);
+        }
+
+        bounds
+    }
+
     pub fn set_layers_to_draw(&mut self, layers_to_draw: Vec<u32>) {
         self.layers_to_draw = layers_to_draw;
     }
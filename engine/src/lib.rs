@@ -19,17 +19,37 @@ pub mod audio_engine;
 pub mod drawable;
 pub mod static_sprite;
 pub mod animated_sprite;
+pub mod sprite_animation;
+pub mod animation_state;
 pub mod texture_registry;
+pub mod texture_atlas;
+pub mod palette;
+pub mod font;
 pub mod timer;
 pub mod vector;
 pub mod rect;
 pub mod offset;
 pub mod extent;
 pub mod transform;
+pub mod camera;
+pub mod viewport;
+pub mod render_layer;
+pub mod particles;
+pub mod lighting;
+pub mod render_target;
+pub mod capture;
+pub mod hot_reload;
+pub mod tween;
 pub mod grid2;
+pub mod generational;
+pub mod as_any;
 pub mod image;
 pub mod game_object;
 pub mod scene;
+pub mod scene_index;
+pub mod scheduler;
+pub mod event_trace;
+pub mod custom_event;
 pub mod linear_force;
 pub mod radial_force;
 
@@ -62,6 +82,7 @@ use sdl2::event::Event;
 pub use sdl2::keyboard::Keycode;
 pub use sdl2::mouse::MouseButton;
 pub use sdl2::pixels::Color;
+pub use sdl2::render::BlendMode;
 
 use audio_engine::WavError;
 
@@ -145,6 +166,10 @@ pub struct Engine<'t> {
     audio_engine: audio_engine::AudioEngine,
     keys_down: HashSet<Keycode>,
     camera: transform::Transform,
+    viewports: Vec<viewport::Viewport>,
+    render_target: Option<texture_registry::Texture>,
+    frame_capture: capture::FrameCapture,
+    hot_reload: hot_reload::HotReload,
     drag_state: Option<MouseDragState>,
     mouse_position: MousePosition,
     window_scale: f32,
@@ -156,7 +181,7 @@ impl<'t> Engine<'t> {
     }
 
     // removed timestuff here
-    pub fn get_draw_context(&mut self) -> drawable::DrawContext {
+    pub fn get_draw_context(&mut self) -> drawable::DrawContext<'_, 't> {
         let bounds = self.get_screen_bounds();
 
         drawable::DrawContext::new(
@@ -172,6 +197,207 @@ impl<'t> Engine<'t> {
         drawable.draw(&mut ctx);
     }
 
+    // Everything drawn through the engine inside `draw`, Scene::render
+    // included, goes into a texture from TextureRegistry::create_render_target
+    // instead of the window. Meanwhile the screen bounds are those of the
+    // target. The target itself can not be drawn from inside `draw`.
+    pub fn with_render_target<F, R>(&mut self, target: &texture_registry::Texture, draw: F) -> Result<R, Error>
+        where F: FnOnce(&mut Engine<'t>) -> R
+    {
+        let previous = self.render_target.replace(target.clone());
+
+        if let Err(error) = self.apply_render_target() {
+            // Nothing was switched, drawing still goes where it did before
+            self.render_target = previous;
+            return Err(error);
+        }
+
+        let result = draw(self);
+
+        self.render_target = previous;
+        self.apply_render_target()?;
+
+        Ok(result)
+    }
+
+    // Same as with_render_target, for drawing with a single context
+    pub fn draw_to_target<F>(&mut self, target: &texture_registry::Texture, clear_color: Option<Color>, draw: F)
+        -> Result<(), Error>
+        where F: FnOnce(&mut drawable::DrawContext)
+    {
+        self.with_render_target(
+            target,
+            |engine| {
+                if let Some(color) = clear_color {
+                    engine.clear(color);
+                }
+
+                let mut ctx = engine.get_draw_context();
+                draw(&mut ctx);
+            }
+        )
+    }
+
+    pub fn get_render_target(&self) -> Option<&texture_registry::Texture> {
+        self.render_target.as_ref()
+    }
+
+    // Fills the window, or the current render target
+    pub fn clear(&mut self, color: Color) {
+        self.canvas.set_draw_color(color);
+        self.canvas.clear();
+    }
+
+    // Reads back what has been drawn to the window, or the current render
+    // target. This is slow, so it is not meant for every frame.
+    pub fn read_pixels(&mut self) -> Result<image::Image<image::RGBA>, Error> {
+        let bounds = self.get_screen_bounds();
+        let width = bounds.max.x as u32;
+        let height = bounds.max.y as u32;
+
+        let data =
+            self.canvas.read_pixels(
+                sdl2::rect::Rect::new(0, 0, width, height),
+                sdl2::pixels::PixelFormatEnum::ABGR8888
+            )?;
+
+        image::Image::from_bytes(width as i32, height as i32, &data)
+    }
+
+    // Reads back the window, or the given render target
+    pub fn capture_frame(&mut self, target: Option<&texture_registry::Texture>) -> Result<image::Image<image::RGBA>, Error> {
+        if let Some(target) = target {
+            return self.with_render_target(target, |engine| engine.read_pixels())?;
+        }
+
+        let previous = self.render_target.take();
+        self.apply_render_target()?;
+
+        let result = self.read_pixels();
+
+        self.render_target = previous;
+        self.apply_render_target()?;
+
+        result
+    }
+
+    // Hotkeys, folders and recording, see FrameCapture
+    pub fn get_frame_capture(&mut self) -> &mut capture::FrameCapture {
+        &mut self.frame_capture
+    }
+
+    // Saved when the frame is done drawing
+    pub fn take_screenshot(&mut self) {
+        self.frame_capture.request_screenshot();
+    }
+
+    pub fn start_recording(&mut self, folder: &str) {
+        self.frame_capture.start_recording(folder);
+    }
+
+    pub fn stop_recording(&mut self) {
+        self.frame_capture.stop_recording();
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.frame_capture.is_recording()
+    }
+
+    fn capture_end_of_frame(&mut self) {
+        if !self.frame_capture.wants_frame() {
+            return;
+        }
+
+        // A failed capture should not take the game down with it
+        let result =
+            self.capture_frame(None)
+                .and_then(|frame| self.frame_capture.end_frame(&frame));
+
+        if let Err(e) = result {
+            println!("Could not capture frame: {:?}", e);
+            self.frame_capture.stop_recording();
+        }
+    }
+
+    // Development mode that reloads textures and sounds when their files
+    // change. Levels are re-read by the game state, see assets_changed.
+    pub fn enable_hot_reload(&mut self) {
+        println!("Watching assets for changes");
+        self.hot_reload.set_enabled(true);
+    }
+
+    pub fn get_hot_reload(&mut self) -> &mut hot_reload::HotReload {
+        &mut self.hot_reload
+    }
+
+    // For files the engine does not load itself, like level data and the
+    // images packed into atlases. Textures and sounds are watched anyway.
+    pub fn watch_assets(&mut self, paths: &[String]) {
+        for path in paths.iter() {
+            self.hot_reload.watch(path);
+        }
+    }
+
+    // Whether any of the files changed since the last frame. Always false
+    // unless hot reload is enabled, and only watched files are noticed, see
+    // watch_assets.
+    pub fn assets_changed(&self, paths: &[String]) -> bool {
+        self.hot_reload.has_any_changed(paths)
+    }
+
+    fn update_hot_reload(&mut self, dt: f32) {
+        if !self.hot_reload.is_enabled() {
+            return;
+        }
+
+        // Everything loaded so far, so nothing has to be registered by hand
+        for path in self.texture_registry.get_loaded_paths() {
+            self.hot_reload.watch(&path);
+        }
+        for filename in self.audio_engine.get_loaded_files() {
+            self.hot_reload.watch(&filename);
+        }
+
+        if !self.hot_reload.update(dt) {
+            return;
+        }
+
+        // Files may be half written when polled, so errors only get logged
+        for path in self.hot_reload.get_changed().to_vec() {
+            let result =
+                self.texture_registry.reload(&path)
+                    .and_then(|texture_reloaded| {
+                        let sound_reloaded = self.audio_engine.reload_file(&path)?;
+                        Ok(texture_reloaded || sound_reloaded)
+                    });
+
+            match result {
+                Ok(true) => println!("Reloaded {}", path),
+                Ok(false) => {},
+                Err(e) => println!("Could not reload {}: {:?}", path, e),
+            }
+        }
+    }
+
+    fn apply_render_target(&mut self) -> Result<(), Error> {
+        let texture =
+            match self.render_target {
+                Some(ref target) => self.texture_registry.get_internal_texture(target).raw(),
+                None => std::ptr::null_mut()
+            };
+
+        // The canvas only offers closure based targets, which would keep the
+        // rest of the engine out of reach while drawing. Textures are owned
+        // by the registry and outlive the target.
+        let result = unsafe { sdl2::sys::SDL_SetRenderTarget(self.canvas.raw(), texture) };
+
+        if result != 0 {
+            return Err(Error::SDLError(sdl2::get_error()));
+        }
+
+        Ok(())
+    }
+
     pub fn get_camera(&mut self) -> transform::Transform {
         self.camera.clone()
     }
@@ -196,6 +422,87 @@ impl<'t> Engine<'t> {
         self.camera.set_scale(value * self.window_scale);
     }
 
+    pub fn get_window_scale(&self) -> f32 {
+        self.window_scale
+    }
+
+    // Starts out with a copy of the main camera. A viewport with the same
+    // name is replaced.
+    pub fn add_viewport(&mut self, name: &str, area: rect::Rect2D) {
+        let viewport = viewport::Viewport::new(name, area, self.camera.clone());
+
+        match self.viewports.iter_mut().find(|v| v.get_name() == name) {
+            Some(existing) => { *existing = viewport; },
+            None => { self.viewports.push(viewport); }
+        }
+    }
+
+    pub fn remove_viewport(&mut self, name: &str) -> bool {
+        let count = self.viewports.len();
+        self.viewports.retain(|v| v.get_name() != name);
+        self.viewports.len() != count
+    }
+
+    pub fn clear_viewports(&mut self) {
+        self.viewports.clear();
+    }
+
+    pub fn get_viewport(&self, name: &str) -> Option<&viewport::Viewport> {
+        self.viewports.iter().find(|v| v.get_name() == name)
+    }
+
+    pub fn get_viewport_mut(&mut self, name: &str) -> Option<&mut viewport::Viewport> {
+        self.viewports.iter_mut().find(|v| v.get_name() == name)
+    }
+
+    pub fn get_viewport_names(&self) -> Vec<String> {
+        self.viewports.iter().map(|v| v.get_name().to_string()).collect()
+    }
+
+    pub fn has_viewports(&self) -> bool {
+        !self.viewports.is_empty()
+    }
+
+    pub fn set_viewport_camera_position(&mut self, name: &str, p: vector::Vec2) {
+        if let Some(viewport) = self.get_viewport_mut(name) {
+            viewport.get_camera_mut().set_translation(p);
+        }
+    }
+
+    pub fn set_viewport_camera_zoom(&mut self, name: &str, value: f32) {
+        let scale = value * self.window_scale;
+        if let Some(viewport) = self.get_viewport_mut(name) {
+            viewport.get_camera_mut().set_scale(scale);
+        }
+    }
+
+    pub fn get_viewport_visible_area(&self, name: &str) -> Option<rect::Rect2D> {
+        let bounds = self.get_screen_bounds();
+        self.get_viewport(name).map(|v| v.get_visible_area(bounds))
+    }
+
+    pub fn get_viewport_draw_context(&mut self, name: &str) -> Option<drawable::DrawContext<'_, 't>> {
+        let bounds = self.get_screen_bounds();
+        let viewport = self.viewports.iter().find(|v| v.get_name() == name)?;
+
+        Some(
+            drawable::DrawContext::in_viewport(
+                self.canvas,
+                &self.texture_registry,
+                viewport.get_camera(),
+                viewport.get_screen_rect(bounds)
+            )
+        )
+    }
+
+    // The topmost enabled viewport under the given screen position
+    pub fn get_viewport_at(&self, x: i32, y: i32) -> Option<&str> {
+        let p = vector::Vec2::from_coords(x as f32, y as f32);
+
+        viewport::viewport_at(&self.viewports, self.get_screen_bounds(), p)
+            .map(|v| v.get_name())
+    }
+
     pub fn on_key_down(&mut self, keycode: Keycode) {
         self.keys_down.insert(keycode);
     }
@@ -204,7 +511,13 @@ impl<'t> Engine<'t> {
         self.keys_down.remove(&keycode);
     }
 
+    // Goes through the viewport under the point if there are any
     pub fn screen_to_world(&self, x: i32, y: i32) -> vector::Vec2 {
+        if let Some(name) = self.get_viewport_at(x, y) {
+            let p = vector::Vec2::from_coords(x as f32, y as f32);
+            return self.get_viewport(name).unwrap().screen_to_world(self.get_screen_bounds(), p);
+        }
+
         let mut screen_transform = transform::Transform::new();
         screen_transform.translate(self.get_screen_bounds().max * 0.5);
 
@@ -321,6 +634,14 @@ impl<'t> Engine<'t> {
 
     // TODO: Make it work with moving camera
     pub fn get_screen_bounds(&self) -> rect::Rect2D {
+        if let Some(ref target) = self.render_target {
+            let extent = target.extent();
+            return rect::Rect2D::new(
+                vector::Vec2::new(),
+                vector::Vec2::from_coords(extent.width as f32, extent.height as f32)
+            );
+        }
+
         rect::Rect2D {
             min: vector::Vec2::new(),
             max: vector::Vec2 {
@@ -371,6 +692,10 @@ impl<'t> Engine<'t> {
                 audio_engine: audio_engine::AudioEngine::new(sdl_context.audio()?),
                 keys_down: HashSet::new(),
                 camera: transform::Transform::new(),
+                viewports: Vec::new(),
+                render_target: None,
+                frame_capture: capture::FrameCapture::new(),
+                hot_reload: hot_reload::HotReload::new(),
                 drag_state: None,
                 mouse_position: MousePosition::new(vector::Vec2{x: 0.0, y: 0.0}),
                 window_scale: 1.0,
@@ -428,6 +753,12 @@ impl<'t> Engine<'t> {
 
                             engine.camera.set_scale(engine.camera.get_scale() * revert_scale);
 
+                            for viewport in engine.viewports.iter_mut() {
+                                let camera = viewport.get_camera_mut();
+                                let scale = camera.get_scale();
+                                camera.set_scale(scale * revert_scale);
+                            }
+
                             engine.width = window_size.0;
                             engine.height = window_size.1;
 
@@ -440,6 +771,10 @@ impl<'t> Engine<'t> {
 
                             timer.reset();
                         }
+                        if !is_repeated {
+                            engine.frame_capture.on_key_down(key);
+                        }
+
                         engine.on_key_down(key);
 
                         current_game_state.on_key_down(&mut engine, key, is_repeated)?;
@@ -487,10 +822,14 @@ impl<'t> Engine<'t> {
             engine.canvas.clear();
 
 
+            engine.update_hot_reload(dt);
+
             current_game_state = current_game_state.update(&mut engine, dt)?;
 
             current_game_state.draw(&mut engine, dt)?;
 
+            engine.capture_end_of_frame();
+
             engine.canvas.present();
 
             // Limit framerate to 100 fps
@@ -0,0 +1,404 @@
+use std::collections::HashMap;
+use std::f32::consts::PI;
+
+use drawable::{DrawParams, Origin};
+use generational::{GenerationalArena, GenerationalIndex};
+use render_target::RenderTarget;
+use scene::{Scene, SceneObjectId};
+use texture_registry::Texture;
+use transform::Transform;
+use vector::Vec2;
+use BlendMode;
+use Color;
+use Engine;
+use Error;
+
+// Size in pixels of the generated light textures, they are scaled to the
+// radius of each light
+const GRADIENT_SIZE: u32 = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LightShape {
+    Point,
+    // Direction in radians, 0 is right and PI/2 is down. Spread is the
+    // full opening angle.
+    Cone { direction: f32, spread: f32 },
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Light {
+    pub position: Vec2,
+    pub radius: f32,
+    pub color: Color,
+    pub intensity: f32,
+    pub shape: LightShape,
+    pub enabled: bool,
+}
+
+impl Light {
+    pub fn point(radius: f32) -> Light {
+        Light {
+            position: Vec2::new(),
+            radius,
+            color: Color::RGB(255, 255, 255),
+            intensity: 1.0,
+            shape: LightShape::Point,
+            enabled: true,
+        }
+    }
+
+    pub fn cone(radius: f32, direction: f32, spread: f32) -> Light {
+        Light {
+            shape: LightShape::Cone { direction, spread },
+            ..Light::point(radius)
+        }
+    }
+
+    pub fn with_position(mut self, position: Vec2) -> Light {
+        self.position = position;
+        self
+    }
+
+    pub fn with_color(mut self, color: Color) -> Light {
+        self.color = color;
+        self
+    }
+
+    // 0.0 is off and 1.0 is full strength
+    pub fn with_intensity(mut self, intensity: f32) -> Light {
+        self.intensity = intensity;
+        self
+    }
+
+    pub fn set_direction(&mut self, direction: f32) {
+        if let LightShape::Cone { spread, .. } = self.shape {
+            self.shape = LightShape::Cone { direction, spread };
+        }
+    }
+}
+
+#[derive(Hash, Eq, PartialEq, Clone, Copy, Debug)]
+pub struct LightId {
+    id: GenerationalIndex,
+}
+
+struct SceneLight {
+    light: Light,
+    // Offset from the object, used instead of the position of the light. The
+    // direction of a cone is relative to the angle of the object.
+    attached_to: Option<(SceneObjectId, Vec2)>,
+    world_position: Vec2,
+    world_direction: f32,
+}
+
+// Light is gathered in an offscreen target that starts out as the ambient
+// colour. The lightmap and every light are added to it, and the result is
+// multiplied with whatever was drawn to the screen before `render`.
+pub struct Lighting {
+    ambient: Color,
+    lightmap: Option<(Texture, Vec2, f32)>,
+    lightmap_enabled: bool,
+    lights: GenerationalArena<SceneLight>,
+    enabled: bool,
+    target: Option<RenderTarget>,
+    point_texture: Option<Texture>,
+    // By spread in whole degrees
+    cone_textures: HashMap<i32, Texture>,
+}
+
+impl Default for Lighting {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Lighting {
+    pub fn new() -> Lighting {
+        Lighting {
+            ambient: Color::RGB(255, 255, 255),
+            lightmap: None,
+            lightmap_enabled: true,
+            lights: GenerationalArena::new(),
+            enabled: true,
+            target: None,
+            point_texture: None,
+            cone_textures: HashMap::new(),
+        }
+    }
+
+    pub fn with_ambient(mut self, ambient: Color) -> Lighting {
+        self.ambient = ambient;
+        self
+    }
+
+    pub fn get_ambient(&self) -> Color {
+        self.ambient
+    }
+
+    pub fn set_ambient(&mut self, ambient: Color) {
+        self.ambient = ambient;
+    }
+
+    // A baked light texture placed in the world with its top left corner at
+    // `top_left`, and `scale` world units per pixel
+    pub fn set_lightmap(&mut self, texture: Texture, top_left: Vec2, scale: f32) {
+        self.lightmap = Some((texture, top_left, scale));
+    }
+
+    pub fn clear_lightmap(&mut self) {
+        self.lightmap = None;
+    }
+
+    pub fn has_lightmap(&self) -> bool {
+        self.lightmap.is_some()
+    }
+
+    pub fn set_lightmap_enabled(&mut self, enabled: bool) {
+        self.lightmap_enabled = enabled;
+    }
+
+    pub fn is_lightmap_enabled(&self) -> bool {
+        self.lightmap_enabled
+    }
+
+    // Without lighting `render` draws nothing and the scene is fully lit
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn add_light(&mut self, light: Light) -> LightId {
+        let direction =
+            match light.shape {
+                LightShape::Cone { direction, .. } => direction,
+                LightShape::Point => 0.0
+            };
+
+        let scene_light =
+            SceneLight {
+                light,
+                attached_to: None,
+                world_position: light.position,
+                world_direction: direction,
+            };
+
+        LightId { id: self.lights.insert(scene_light) }
+    }
+
+    pub fn remove_light(&mut self, id: LightId) -> bool {
+        self.lights.remove(id.id).is_some()
+    }
+
+    pub fn has_light(&self, id: LightId) -> bool {
+        self.lights.contains(id.id)
+    }
+
+    pub fn get_light(&self, id: LightId) -> Option<&Light> {
+        self.lights.get(id.id).map(|l| &l.light)
+    }
+
+    pub fn get_light_mut(&mut self, id: LightId) -> Option<&mut Light> {
+        self.lights.get_mut(id.id).map(|l| &mut l.light)
+    }
+
+    // Moves the light along with the object, see `update`. The light is
+    // removed along with the object.
+    pub fn attach_light(&mut self, id: LightId, object: SceneObjectId, offset: Vec2) -> bool {
+        match self.lights.get_mut(id.id) {
+            Some(l) => {
+                l.attached_to = Some((object, offset));
+                true
+            },
+            None => false
+        }
+    }
+
+    pub fn detach_light(&mut self, id: LightId) {
+        if let Some(l) = self.lights.get_mut(id.id) {
+            l.attached_to = None;
+        }
+    }
+
+    // Follows the objects lights are attached to
+    pub fn update(&mut self, scene: &Scene) {
+        let mut removed = Vec::new();
+
+        for (id, l) in self.lights.iter_mut() {
+            let base_direction =
+                match l.light.shape {
+                    LightShape::Cone { direction, .. } => direction,
+                    LightShape::Point => 0.0
+                };
+
+            match l.attached_to {
+                Some((object, offset)) => {
+                    match scene.get_world_transform(object) {
+                        Some(transform) => {
+                            l.world_position = transform.get_translation() + offset;
+                            // Sprites are drawn turning the other way around
+                            l.world_direction = base_direction - transform.get_angle();
+                        },
+                        None => { removed.push(id); }
+                    }
+                },
+                None => {
+                    l.world_position = l.light.position;
+                    l.world_direction = base_direction;
+                }
+            }
+        }
+
+        for id in removed {
+            self.lights.remove(id);
+        }
+    }
+
+    // Darkens everything drawn so far, so it goes after the scene and before
+    // any user interface
+    pub fn render(&mut self, engine: &mut Engine) -> Result<(), Error> {
+        if !self.enabled {
+            return Ok(());
+        }
+
+        // Nothing gets brighter than white, so lights would not change a
+        // white ambient either and the whole pass can be skipped
+        if self.ambient.r == 255 && self.ambient.g == 255 && self.ambient.b == 255 {
+            self.target = None;
+            return Ok(());
+        }
+
+        let mut target =
+            match self.target.take() {
+                Some(target) => target,
+                None => RenderTarget::screen_sized(engine)?
+            };
+        target.fit_to_screen(engine)?;
+
+        let mut draws = Vec::new();
+
+        for (_, l) in self.lights.iter() {
+            let light = &l.light;
+
+            if !light.enabled || light.intensity <= 0.0 || light.radius <= 0.0 {
+                continue;
+            }
+
+            let mut transform = Transform::new();
+            transform.set_translation(l.world_position);
+            transform.set_scale(light.radius * 2.0 / GRADIENT_SIZE as f32);
+
+            let texture =
+                match light.shape {
+                    LightShape::Point => {
+                        if self.point_texture.is_none() {
+                            let data = light_gradient(GRADIENT_SIZE, None);
+                            self.point_texture =
+                                Some(engine.get_texture_registry().create_from_rgba(GRADIENT_SIZE, GRADIENT_SIZE, &data, BlendMode::Add)?);
+                        }
+
+                        self.point_texture.clone().unwrap()
+                    },
+                    LightShape::Cone { spread, .. } => {
+                        let degrees = spread.to_degrees().round() as i32;
+
+                        // The texture points right
+                        transform.set_angle(-l.world_direction);
+
+                        match self.cone_textures.get(&degrees) {
+                            Some(texture) => texture.clone(),
+                            None => {
+                                let data = light_gradient(GRADIENT_SIZE, Some((degrees as f32).to_radians()));
+                                let texture =
+                                    engine.get_texture_registry().create_from_rgba(GRADIENT_SIZE, GRADIENT_SIZE, &data, BlendMode::Add)?;
+                                self.cone_textures.insert(degrees, texture.clone());
+                                texture
+                            }
+                        }
+                    }
+                };
+
+            let params =
+                DrawParams::new()
+                    .with_color(light.color)
+                    .with_alpha((light.intensity.min(1.0) * 255.0) as u8)
+                    .with_blend_mode(BlendMode::Add);
+
+            draws.push((texture, transform, params));
+        }
+
+        target.set_clear_color(Some(Color::RGBA(self.ambient.r, self.ambient.g, self.ambient.b, 255)));
+        let lightmap = if self.lightmap_enabled { self.lightmap.clone() } else { None };
+
+        target.render(
+            engine,
+            |engine| {
+                let mut ctx = engine.get_draw_context();
+
+                if let Some((texture, top_left, scale)) = lightmap {
+                    let mut transform = Transform::new();
+                    transform.set_translation(top_left);
+                    transform.set_scale(scale);
+
+                    let params = DrawParams::new().with_blend_mode(BlendMode::Add);
+                    ctx.draw2_with(&texture, &transform, Origin::TopLeft, &params);
+                }
+
+                for (texture, transform, params) in draws.iter() {
+                    ctx.draw_with(texture, transform, params);
+                }
+            }
+        )?;
+
+        let screen_bounds = engine.get_screen_bounds();
+        let params = DrawParams::new().with_blend_mode(BlendMode::Mod);
+        target.draw_on_screen(engine, screen_bounds, &params);
+
+        self.target = Some(target);
+
+        Ok(())
+    }
+}
+
+// White RGBA pixels with the light falloff in the alpha channel. Cones point
+// right and fade out towards the edges of their spread.
+fn light_gradient(size: u32, spread: Option<f32>) -> Vec<u8> {
+    let mut data = Vec::with_capacity((size * size * 4) as usize);
+    let half = size as f32 * 0.5;
+
+    for y in 0..size {
+        for x in 0..size {
+            let d = Vec2::from_coords(x as f32 + 0.5 - half, y as f32 + 0.5 - half);
+
+            let falloff = (1.0 - d.len() / half).max(0.0);
+            let mut value = falloff * falloff;
+
+            if let Some(spread) = spread {
+                let half_spread = spread * 0.5;
+                // Soften the edges over a tenth of the spread
+                let edge = (spread * 0.1).max(PI / 180.0);
+                let angle = d.y.atan2(d.x).abs();
+
+                value *= ((half_spread - angle) / edge).clamp(0.0, 1.0);
+            }
+
+            data.extend_from_slice(&[255, 255, 255, (value * 255.0).round() as u8]);
+        }
+    }
+
+    data
+}
+
+#[test]
+fn test_cone_gradient_is_dark_behind_the_light() {
+    let size = 64;
+    let data = light_gradient(size, Some(PI * 0.5));
+    let alpha = |x: u32, y: u32| data[((y * size + x) * 4 + 3) as usize];
+
+    assert!(alpha(40, 32) > 0);
+    assert_eq!(alpha(24, 32), 0);
+    assert_eq!(alpha(32, 4), 0);
+    assert_eq!(alpha(63, 32), 0);
+}
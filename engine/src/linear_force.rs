@@ -11,6 +11,14 @@ impl LinearForce {
             direction
         }
     }
+
+    pub fn get_direction(&self) -> Vec2 {
+        self.direction
+    }
+
+    pub fn set_direction(&mut self, direction: Vec2) {
+        self.direction = direction;
+    }
 }
 
 impl Force for LinearForce {
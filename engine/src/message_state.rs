@@ -98,7 +98,7 @@ impl GameState for MessageState {
         let _ignored = self.next_state.draw(ctx, dt);
 
         let mut draw_ctx = ctx.get_draw_context();
-        let ramp = (1.0 - (self.animation * std::f32::consts::PI).cos()) * 0.5;
+        let ramp = Easing::SineInOut.apply(self.animation);
         draw_ctx.draw(&self.message_texture, &self.start_transform.interpolate(&self.target_transform, ramp));
 
         Ok(())
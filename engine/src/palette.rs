@@ -0,0 +1,297 @@
+use std::collections::{HashMap, HashSet};
+
+use sdl2::rect::Rect;
+
+use drawable::DrawParams;
+use image::{Image, RGBA};
+use render_target::PostPass;
+use texture_registry::Texture;
+use BlendMode;
+use Color;
+use Engine;
+use Error;
+
+// An ordered list of colours. Two palettes of the same length describe a
+// recolouring, see PaletteSwap.
+#[derive(Clone, Debug)]
+pub struct Palette {
+    colors: Vec<RGBA>,
+}
+
+impl Palette {
+    pub fn new(colors: Vec<RGBA>) -> Palette {
+        Palette { colors }
+    }
+
+    // Every distinct colour of the image, row by row. Transparent pixels are
+    // left out.
+    pub fn from_image(image: &Image<RGBA>) -> Palette {
+        let mut colors : Vec<RGBA> = Vec::new();
+        let mut seen : HashSet<(u8, u8, u8)> = HashSet::new();
+
+        for pixel in image.data().iter() {
+            if pixel.a > 0 && seen.insert((pixel.r, pixel.g, pixel.b)) {
+                colors.push(*pixel);
+            }
+        }
+
+        Palette { colors }
+    }
+
+    // Palette images like assets/levels/GroundFloor-Palette.png
+    pub fn load(filename: &str) -> Result<Palette, Error> {
+        Ok(Palette::from_image(&Image::load(filename)?))
+    }
+
+    pub fn get_colors(&self) -> &[RGBA] {
+        &self.colors
+    }
+
+    pub fn len(&self) -> usize {
+        self.colors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.colors.is_empty()
+    }
+
+    // The same palette with every colour rotated around the colour wheel,
+    // for variants without a palette of their own
+    pub fn with_hue_shift(&self, degrees: f32) -> Palette {
+        Palette {
+            colors: self.colors.iter().map(|c| shift_hue(*c, degrees)).collect()
+        }
+    }
+}
+
+// Replaces every colour of the source palette with the colour at the same
+// place in the target palette. Pixels keep their own alpha, colours that are
+// not in the source palette stay as they are.
+#[derive(Clone, Debug)]
+pub struct PaletteSwap {
+    mapping: HashMap<(u8, u8, u8), (u8, u8, u8)>,
+}
+
+impl PaletteSwap {
+    // Extra colours of the longer palette are ignored
+    pub fn new(source: &Palette, target: &Palette) -> PaletteSwap {
+        let mapping =
+            source.colors.iter()
+                .zip(target.colors.iter())
+                .map(|(from, to)| ((from.r, from.g, from.b), (to.r, to.g, to.b)))
+                .collect();
+
+        PaletteSwap { mapping }
+    }
+
+    pub fn with_color(mut self, from: Color, to: Color) -> PaletteSwap {
+        self.mapping.insert((from.r, from.g, from.b), (to.r, to.g, to.b));
+        self
+    }
+
+    pub fn remap(&self, image: &mut Image<RGBA>) {
+        for pixel in image.data_mut().iter_mut() {
+            if let Some(&(r, g, b)) = self.mapping.get(&(pixel.r, pixel.g, pixel.b)) {
+                pixel.r = r;
+                pixel.g = g;
+                pixel.b = b;
+            }
+        }
+    }
+
+    // A new texture with the colours swapped. The pixels are read back from
+    // the texture, so this is meant for loading time rather than every frame.
+    pub fn remap_texture(&self, ctx: &mut Engine, texture: &Texture) -> Result<Texture, Error> {
+        let extent = texture.extent();
+        let (width, height) = (extent.width as u32, extent.height as u32);
+
+        let target = ctx.get_texture_registry().create_render_target(width, height)?;
+
+        let src = Rect::new(texture.offset().x, texture.offset().y, width, height);
+        let dst = Rect::new(0, 0, width, height);
+        // Copied as it is, so the alpha of the texture survives
+        let params = DrawParams::new().with_blend_mode(BlendMode::None);
+
+        ctx.draw_to_target(
+            &target,
+            Some(Color::RGBA(0, 0, 0, 0)),
+            |ctx| ctx.copy_ex_with(texture, src, dst, 0.0, &params)
+        )?;
+
+        let mut image = ctx.capture_frame(Some(&target))?;
+        self.remap(&mut image);
+
+        ctx.get_texture_registry().create_from_image(&image, BlendMode::Blend)
+    }
+}
+
+impl PostPass for PaletteSwap {
+    fn apply(&mut self, image: &mut Image<RGBA>) {
+        self.remap(image);
+    }
+}
+
+// Named palette swaps and the textures made with them, so every variant of
+// an image is only recoloured once.
+//
+//     let mut palettes = PaletteCache::new();
+//     palettes.add_variant("red", PaletteSwap::new(&source, &red));
+//     let texture = palettes.get(ctx, "assets/images/tower/victim.png", "red")?;
+pub struct PaletteCache {
+    variants: HashMap<String, PaletteSwap>,
+    textures: HashMap<(String, String), Texture>,
+}
+
+impl Default for PaletteCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PaletteCache {
+    pub fn new() -> PaletteCache {
+        PaletteCache {
+            variants: HashMap::new(),
+            textures: HashMap::new(),
+        }
+    }
+
+    // Replacing a variant drops the textures made with the old one
+    pub fn add_variant(&mut self, name: &str, swap: PaletteSwap) {
+        self.variants.insert(name.to_string(), swap);
+        self.textures.retain(|(_, variant), _| variant != name);
+    }
+
+    pub fn has_variant(&self, name: &str) -> bool {
+        self.variants.contains_key(name)
+    }
+
+    pub fn get_variant_names(&self) -> Vec<&str> {
+        let mut names : Vec<&str> = self.variants.keys().map(|name| name.as_str()).collect();
+        names.sort();
+        names
+    }
+
+    // The image at `path` in the colours of the variant
+    pub fn get(&mut self, ctx: &mut Engine, path: &str, variant: &str) -> Result<Texture, Error> {
+        let key = (path.to_string(), variant.to_string());
+
+        if let Some(texture) = self.textures.get(&key) {
+            return Ok(texture.clone());
+        }
+
+        let swap =
+            self.variants.get(variant)
+                .ok_or_else(|| Error::FatalError(format!("Unknown palette variant {}", variant)))?;
+
+        let mut image = Image::load(path)?;
+        swap.remap(&mut image);
+
+        let texture = ctx.get_texture_registry().create_from_image(&image, BlendMode::Blend)?;
+        self.textures.insert(key, texture.clone());
+
+        Ok(texture)
+    }
+
+    // Lets go of the textures, they are freed with the next purge of the
+    // texture registry unless still in use
+    pub fn clear_textures(&mut self) {
+        self.textures.clear();
+    }
+}
+
+fn shift_hue(color: RGBA, degrees: f32) -> RGBA {
+    let (r, g, b) = (color.r as f32 / 255.0, color.g as f32 / 255.0, color.b as f32 / 255.0);
+
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let chroma = max - min;
+
+    if chroma <= 0.0 {
+        return color;
+    }
+
+    let hue =
+        if max == r {
+            60.0 * ((g - b) / chroma)
+        } else if max == g {
+            60.0 * ((b - r) / chroma + 2.0)
+        } else {
+            60.0 * ((r - g) / chroma + 4.0)
+        };
+
+    let hue = (hue + degrees).rem_euclid(360.0) / 60.0;
+    let x = chroma * (1.0 - ((hue % 2.0) - 1.0).abs());
+
+    let (r, g, b) =
+        match hue as i32 {
+            0 => (chroma, x, 0.0),
+            1 => (x, chroma, 0.0),
+            2 => (0.0, chroma, x),
+            3 => (0.0, x, chroma),
+            4 => (x, 0.0, chroma),
+            _ => (chroma, 0.0, x),
+        };
+
+    let to_byte = |v: f32| ((v + min) * 255.0).round().clamp(0.0, 255.0) as u8;
+
+    RGBA { r: to_byte(r), g: to_byte(g), b: to_byte(b), a: color.a }
+}
+
+#[test]
+fn test_palette_swap_keeps_alpha_and_unknown_colors() {
+    let bytes = [
+        255, 0, 0, 255,   255, 0, 0, 128,   10, 20, 30, 0,
+    ];
+    let mut image : Image<RGBA> = Image::from_bytes(3, 1, &bytes).unwrap();
+
+    let source = Palette::from_image(&image);
+    assert_eq!(source.len(), 1);
+
+    let target = source.with_hue_shift(120.0);
+    PaletteSwap::new(&source, &target).remap(&mut image);
+
+    assert_eq!(image.get_pixel(0, 0), Some(&RGBA { r: 0, g: 255, b: 0, a: 255 }));
+    assert_eq!(image.get_pixel(1, 0), Some(&RGBA { r: 0, g: 255, b: 0, a: 128 }));
+    assert_eq!(image.get_pixel(2, 0), Some(&RGBA { r: 10, g: 20, b: 30, a: 0 }));
+}
+
+#[test]
+fn test_palette_cache_variants() {
+    let red = Palette::new(vec![RGBA { r: 255, g: 0, b: 0, a: 255 }]);
+    let swap = PaletteSwap::new(&red, &red.with_hue_shift(120.0));
+
+    let mut palettes = PaletteCache::new();
+    palettes.add_variant("green", swap.clone());
+    palettes.add_variant("blue", PaletteSwap::new(&red, &red.with_hue_shift(240.0)));
+
+    assert!(palettes.has_variant("green"));
+    assert!(!palettes.has_variant("red"));
+    assert_eq!(palettes.get_variant_names(), vec!["blue", "green"]);
+
+    let path = "assets/images/tower/victim.png".to_string();
+    palettes.textures.insert((path.clone(), "green".to_string()), Texture::detached(4, 4));
+    palettes.textures.insert((path.clone(), "blue".to_string()), Texture::detached(4, 4));
+
+    // Only textures of the replaced variant are made again
+    palettes.add_variant("green", swap);
+    assert!(!palettes.textures.contains_key(&(path.clone(), "green".to_string())));
+    assert!(palettes.textures.contains_key(&(path, "blue".to_string())));
+
+    palettes.clear_textures();
+    assert!(palettes.textures.is_empty());
+    assert_eq!(palettes.get_variant_names().len(), 2);
+}
+
+#[test]
+fn test_palette_from_image_keeps_first_occurrence_order() {
+    let bytes = [
+        0, 0, 255, 255,   255, 0, 0, 255,   0, 0, 255, 128,   0, 255, 0, 0,   255, 0, 0, 255,
+    ];
+    let image : Image<RGBA> = Image::from_bytes(5, 1, &bytes).unwrap();
+
+    let colors : Vec<(u8, u8, u8)> =
+        Palette::from_image(&image).get_colors().iter().map(|c| (c.r, c.g, c.b)).collect();
+
+    assert_eq!(colors, vec![(0, 0, 255), (255, 0, 0)]);
+}
@@ -0,0 +1,370 @@
+use std::fs::File;
+use std::io::Read;
+
+use rand::Rng;
+use rand;
+use serde_json;
+
+use animated_sprite::AnimatedSprite;
+use drawable::{DrawContext, DrawParams, Drawable};
+use extent::Extent;
+use offset::Offset;
+use texture_registry::Texture;
+use transform::Transform;
+use tween::Tweenable;
+use vector::Vec2;
+use Color;
+use Engine;
+use Error;
+
+// Everything about an emitter that can be tweaked without recompiling. All
+// fields have defaults, so the JSON only needs the ones that matter:
+//
+//     {
+//         "texture": "assets/images/tower/blood.png",
+//         "burst": 20,
+//         "lifetime": [0.5, 1.0],
+//         "speed": [100.0, 400.0],
+//         "angle": [-150.0, -30.0],
+//         "end_color": [255, 255, 255, 0]
+//     }
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct EmitterDefinition {
+    pub texture: String,
+    // Splits the texture into animation frames of this size, read left to
+    // right, top to bottom
+    pub frame_size: Option<(i32, i32)>,
+    pub frames_per_second: f32,
+    pub max_particles: usize,
+    // Particles emitted right away when the emitter starts
+    pub burst: u32,
+    // Particles per second while emitting
+    pub rate: f32,
+    // Seconds of continuous emission, forever if not set
+    pub duration: Option<f32>,
+    pub lifetime: [f32; 2],
+    pub speed: [f32; 2],
+    // Direction in degrees, 0 is right and 90 is down
+    pub angle: [f32; 2],
+    pub spin: [f32; 2],
+    pub gravity: Vec2,
+    // Whether scene forces like wind and gravity wells push the particles
+    pub affected_by_forces: bool,
+    pub start_color: [u8; 4],
+    pub end_color: [u8; 4],
+    pub start_scale: f32,
+    pub end_scale: f32,
+}
+
+impl Default for EmitterDefinition {
+    fn default() -> Self {
+        EmitterDefinition {
+            texture: String::new(),
+            frame_size: None,
+            frames_per_second: 10.0,
+            max_particles: 256,
+            burst: 0,
+            rate: 0.0,
+            duration: None,
+            lifetime: [1.0, 1.0],
+            speed: [0.0, 0.0],
+            angle: [0.0, 360.0],
+            spin: [0.0, 0.0],
+            gravity: Vec2::new(),
+            affected_by_forces: false,
+            start_color: [255, 255, 255, 255],
+            end_color: [255, 255, 255, 255],
+            start_scale: 1.0,
+            end_scale: 1.0,
+        }
+    }
+}
+
+impl EmitterDefinition {
+    pub fn load(filename: &str) -> Result<EmitterDefinition, Error> {
+        let mut data = String::new();
+
+        File::open(filename)
+            .and_then(|mut f| f.read_to_string(&mut data))
+            .map_err(|_| Error::IO { path: Some(filename.to_string()) })?;
+
+        serde_json::from_str::<EmitterDefinition>(&data)
+            .map_err(|e| Error::FatalError(format!("Could not parse {}: {}", filename, e)))
+    }
+}
+
+#[derive(Clone)]
+struct Particle {
+    position: Vec2,
+    velocity: Vec2,
+    angle: f32,
+    spin: f32,
+    age: f32,
+    lifetime: f32,
+}
+
+// Lightweight particles without physics shapes or events, updated and drawn
+// in bulk. Emitters can be drawn on their own, or given to a Scene which
+// updates them, applies its forces and moves them along with objects.
+#[derive(Clone)]
+pub struct ParticleEmitter {
+    definition: EmitterDefinition,
+    frames: Vec<Texture>,
+    particles: Vec<Particle>,
+    position: Vec2,
+    emitting: bool,
+    started: bool,
+    elapsed: f32,
+    pending: f32,
+}
+
+impl ParticleEmitter {
+    pub fn new(definition: EmitterDefinition, texture: Texture) -> Result<ParticleEmitter, Error> {
+        let frames =
+            match definition.frame_size {
+                Some((width, height)) => split_frames(&texture, Extent::new(width, height))?,
+                None => vec![texture]
+            };
+
+        Ok(ParticleEmitter::with_frames(definition, frames))
+    }
+
+    // Uses the frames of one mode of the sprite
+    pub fn from_animated_sprite(definition: EmitterDefinition, sprite: &AnimatedSprite, mode: i32) -> ParticleEmitter {
+        ParticleEmitter::with_frames(definition, sprite.get_frames(mode))
+    }
+
+    pub fn with_frames(definition: EmitterDefinition, frames: Vec<Texture>) -> ParticleEmitter {
+        ParticleEmitter {
+            definition,
+            frames,
+            particles: Vec::new(),
+            position: Vec2::new(),
+            emitting: true,
+            started: false,
+            elapsed: 0.0,
+            pending: 0.0,
+        }
+    }
+
+    // Loads the definition and the texture it names
+    pub fn load(ctx: &mut Engine, filename: &str) -> Result<ParticleEmitter, Error> {
+        let definition = EmitterDefinition::load(filename)?;
+        let texture = ctx.get_texture_registry().load(&definition.texture)?;
+
+        ParticleEmitter::new(definition, texture)
+    }
+
+    pub fn get_definition(&self) -> &EmitterDefinition {
+        &self.definition
+    }
+
+    pub fn get_definition_mut(&mut self) -> &mut EmitterDefinition {
+        &mut self.definition
+    }
+
+    pub fn get_position(&self) -> Vec2 {
+        self.position
+    }
+
+    pub fn set_position(&mut self, position: Vec2) {
+        self.position = position;
+    }
+
+    pub fn get_particle_count(&self) -> usize {
+        self.particles.len()
+    }
+
+    // Starts over, including the initial burst
+    pub fn start(&mut self) {
+        self.emitting = true;
+        self.started = false;
+        self.elapsed = 0.0;
+        self.pending = 0.0;
+    }
+
+    // Particles that are alive keep going
+    pub fn stop(&mut self) {
+        self.emitting = false;
+    }
+
+    pub fn is_emitting(&self) -> bool {
+        self.emitting
+    }
+
+    // Done emitting and every particle has died
+    pub fn is_finished(&self) -> bool {
+        self.started && !self.emitting && self.particles.is_empty()
+    }
+
+    pub fn burst(&mut self, count: u32) {
+        for _ in 0..count {
+            self.spawn();
+        }
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.update_with_forces(dt, |_| Vec2::new());
+    }
+
+    // `force` gives the extra acceleration at a position, e.g. from scene
+    // forces, and is only used if the definition asks for it
+    pub fn update_with_forces<F: Fn(Vec2) -> Vec2>(&mut self, dt: f32, force: F) {
+        if !self.started {
+            self.started = true;
+
+            if self.emitting {
+                let burst = self.definition.burst;
+                self.burst(burst);
+            }
+        }
+
+        if self.emitting {
+            self.elapsed += dt;
+            self.pending += self.definition.rate * dt;
+
+            while self.pending >= 1.0 {
+                self.pending -= 1.0;
+                self.spawn();
+            }
+
+            let duration_over = self.definition.duration.map(|d| self.elapsed >= d).unwrap_or(false);
+            let one_shot = self.definition.rate <= 0.0;
+
+            if duration_over || one_shot {
+                self.emitting = false;
+            }
+        }
+
+        let gravity = self.definition.gravity;
+        let affected_by_forces = self.definition.affected_by_forces;
+
+        for particle in self.particles.iter_mut() {
+            let mut acceleration = gravity;
+            if affected_by_forces {
+                acceleration += force(particle.position);
+            }
+
+            particle.velocity += acceleration * dt;
+            particle.position += particle.velocity * dt;
+            particle.angle += particle.spin * dt;
+            particle.age += dt;
+        }
+
+        self.particles.retain(|p| p.age < p.lifetime);
+    }
+
+    fn spawn(&mut self) {
+        if self.particles.len() >= self.definition.max_particles {
+            return;
+        }
+
+        let definition = &self.definition;
+        let mut rng = rand::thread_rng();
+        let mut random_in = |range: [f32; 2]| {
+            if range[0] < range[1] { rng.gen_range(range[0]..range[1]) } else { range[0] }
+        };
+
+        let angle = random_in(definition.angle).to_radians();
+        let speed = random_in(definition.speed);
+
+        let particle =
+            Particle {
+                position: self.position,
+                velocity: Vec2::from_coords(angle.cos(), angle.sin()) * speed,
+                angle: 0.0,
+                spin: random_in(definition.spin).to_radians(),
+                age: 0.0,
+                lifetime: random_in(definition.lifetime).max(0.001),
+            };
+
+        self.particles.push(particle);
+    }
+}
+
+impl Drawable for ParticleEmitter {
+    fn draw(&self, ctx: &mut DrawContext) {
+        if self.frames.is_empty() {
+            return;
+        }
+
+        let definition = &self.definition;
+        let to_color = |c: [u8; 4]| Color::RGBA(c[0], c[1], c[2], c[3]);
+        let start_color = to_color(definition.start_color);
+        let end_color = to_color(definition.end_color);
+
+        for particle in self.particles.iter() {
+            let f = particle.age / particle.lifetime;
+            let color = start_color.lerp(&end_color, f);
+
+            let frame = (particle.age * definition.frames_per_second) as usize % self.frames.len();
+
+            let mut transform = Transform::new();
+            transform.set_translation(particle.position);
+            transform.set_angle(particle.angle);
+            transform.set_scale(definition.start_scale.lerp(&definition.end_scale, f));
+
+            let params =
+                DrawParams::new()
+                    .with_color(Color::RGB(color.r, color.g, color.b))
+                    .with_alpha(color.a);
+
+            ctx.draw_with(&self.frames[frame], &transform, &params);
+        }
+    }
+}
+
+fn split_frames(texture: &Texture, frame_extent: Extent) -> Result<Vec<Texture>, Error> {
+    if frame_extent.width <= 0 || frame_extent.height <= 0 {
+        return Err(Error::InvalidTileSize);
+    }
+
+    let columns = texture.extent().width / frame_extent.width;
+    let rows = texture.extent().height / frame_extent.height;
+
+    let mut frames = Vec::new();
+
+    for y in 0..rows {
+        for x in 0..columns {
+            let offset = Offset::from_coords(x * frame_extent.width, y * frame_extent.height);
+            frames.push(texture.sub_texture(offset, frame_extent)?);
+        }
+    }
+
+    Ok(frames)
+}
+
+#[test]
+fn test_emitter_lifetime() {
+    let definition =
+        EmitterDefinition {
+            max_particles: 4,
+            burst: 3,
+            rate: 2.0,
+            duration: Some(1.0),
+            lifetime: [0.75, 0.75],
+            speed: [10.0, 10.0],
+            angle: [90.0, 90.0],
+            ..EmitterDefinition::default()
+        };
+
+    let mut emitter = ParticleEmitter::with_frames(definition, Vec::new());
+    emitter.set_position(Vec2::from_coords(100.0, 0.0));
+    assert_eq!(emitter.get_particle_count(), 0);
+
+    // The burst comes with the first update, the rate adds one more
+    emitter.update(0.5);
+    assert_eq!(emitter.get_particle_count(), 4);
+    assert!(emitter.is_emitting());
+
+    for particle in emitter.particles.iter() {
+        assert!((particle.position - Vec2::from_coords(100.0, 5.0)).len() < 0.001);
+    }
+
+    // Full, so nothing new is spawned, and the duration is over
+    emitter.update(0.5);
+    assert!(!emitter.is_emitting());
+    assert_eq!(emitter.get_particle_count(), 0);
+    assert!(emitter.is_finished());
+}
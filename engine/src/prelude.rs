@@ -7,15 +7,21 @@ pub use MouseButton;
 pub use GameState;
 pub use static_sprite::StaticSprite;
 pub use animated_sprite::{Animatable, AnimatedSprite, AggregatedAnimatedSprite, SpriteTrait};
-pub use drawable::{Drawable, DrawContext, Origin};
+pub use sprite_animation::{AnimationDefinition, AnimationEvent, ClipDefinition, FrameDefinition, PlayMode, SpriteAnimation};
+pub use animation_state::{AnimationStateMachine, Condition, Transition};
+pub use drawable::{Drawable, DrawContext, DrawParams, Origin};
 pub use vector::{Vec2, Polar2};
 pub use extent::Extent;
 pub use offset::Offset;
-pub use texture_registry::Texture;
+pub use texture_registry::{Texture, TextureStats};
+pub use texture_atlas::{AtlasBuilder, PackedAtlas, TextureAtlas};
+pub use palette::{Palette, PaletteSwap, PaletteCache};
+pub use font::{Font, TextAlign, TextStyle};
 pub use rect::Rect2D;
 
 pub use transition_state::TransitionState;
 pub use Color;
+pub use BlendMode;
 pub use message_state::{
     Animation,
     ProceedMode,
@@ -31,6 +37,23 @@ pub use trigger::Trigger;
 pub use image::{Image, RGBA};
 
 pub use transform::Transform;
+pub use camera::CameraController;
+pub use viewport::Viewport;
+pub use render_layer::RenderLayer;
+pub use particles::{EmitterDefinition, ParticleEmitter};
+pub use lighting::{Light, LightId, LightShape, Lighting};
+pub use render_target::{RenderTarget, PostPass, Pixelate, ColorFade};
+pub use capture::FrameCapture;
+pub use hot_reload::HotReload;
+pub use tween::{
+    Easing,
+    Tweenable,
+    Tweening,
+    Tween,
+    TweenSequence,
+    TweenGroup,
+    Repeat
+};
 
 pub use game_object::{
     CollisionShape,
@@ -40,10 +63,20 @@ pub use game_object::{
     EventType,
     EventReceiver,
     GameEvent,
-    EventMailbox
+    EventMailbox,
+    EventPriority,
+    SceneCommand
+};
+pub use custom_event::{
+    CustomEvent,
+    TypedEventMailbox,
+    EventHandlers
 };
+pub use scene_index::SceneIndex;
+pub use scheduler::{TimerId, Sequence, SequenceId};
 pub use scene::{
     SceneForceId,
+    ParticleEmitterId,
     Force,
     SceneObjectId,
     Scene
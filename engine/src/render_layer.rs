@@ -0,0 +1,109 @@
+use transform::Transform;
+
+pub const DEFAULT_LAYER: &str = "default";
+
+// A named group of scene objects drawn together. Layers are drawn by order,
+// lowest first, and objects within a layer by z-index.
+//
+// The parallax factor scales how far the layer moves with the camera: 1.0
+// moves with the world, 0.5 is a distant background and 0.0 sticks to the
+// screen. A layer with its own zoom ignores the zoom of the camera.
+#[derive(Clone, Debug)]
+pub struct RenderLayer {
+    name: String,
+    order: i32,
+    parallax: f32,
+    zoom: Option<f32>,
+    visible: bool,
+}
+
+impl RenderLayer {
+    pub fn new(name: &str, order: i32) -> RenderLayer {
+        RenderLayer {
+            name: name.to_string(),
+            order,
+            parallax: 1.0,
+            zoom: None,
+            visible: true,
+        }
+    }
+
+    pub fn with_parallax(mut self, parallax: f32) -> RenderLayer {
+        self.parallax = parallax;
+        self
+    }
+
+    pub fn with_zoom(mut self, zoom: f32) -> RenderLayer {
+        self.zoom = Some(zoom);
+        self
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn get_order(&self) -> i32 {
+        self.order
+    }
+
+    pub fn get_parallax(&self) -> f32 {
+        self.parallax
+    }
+
+    pub fn set_parallax(&mut self, parallax: f32) {
+        self.parallax = parallax;
+    }
+
+    pub fn get_zoom(&self) -> Option<f32> {
+        self.zoom
+    }
+
+    pub fn set_zoom(&mut self, zoom: Option<f32>) {
+        self.zoom = zoom;
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    pub fn set_visible(&mut self, visible: bool) {
+        self.visible = visible;
+    }
+
+    // The camera this layer is drawn with
+    pub fn apply_to_camera(&self, camera: &Transform, window_scale: f32) -> Transform {
+        let mut result = camera.clone();
+        result.set_translation(camera.get_translation() * self.parallax);
+
+        if let Some(zoom) = self.zoom {
+            result.set_scale(zoom * window_scale);
+        }
+
+        result
+    }
+}
+
+#[test]
+fn test_layer_camera() {
+    use vector::Vec2;
+
+    let mut camera = Transform::new();
+    camera.set_translation(Vec2::from_coords(200.0, -100.0));
+    camera.set_scale(3.0);
+
+    // The world layer uses the camera as is
+    let world = RenderLayer::new(DEFAULT_LAYER, 0).apply_to_camera(&camera, 2.0);
+    assert_eq!(world, camera);
+
+    // Distant layers move less, screen layers not at all
+    let distant = RenderLayer::new("hills", -1).with_parallax(0.5).apply_to_camera(&camera, 2.0);
+    assert_eq!(distant.get_translation(), Vec2::from_coords(100.0, -50.0));
+    assert_eq!(distant.get_scale(), 3.0);
+
+    let screen = RenderLayer::new("sky", -2).with_parallax(0.0).apply_to_camera(&camera, 2.0);
+    assert_eq!(screen.get_translation(), Vec2::new());
+
+    // A fixed zoom ignores the camera zoom, but not the window scale
+    let hud = RenderLayer::new("hud", 1).with_parallax(0.0).with_zoom(1.0).apply_to_camera(&camera, 2.0);
+    assert_eq!(hud.get_scale(), 2.0);
+}
@@ -0,0 +1,243 @@
+use sdl2::rect::Rect;
+
+use drawable::DrawParams;
+use extent::Extent;
+use image::{Image, RGBA};
+use rect::Rect2D;
+use texture_registry::Texture;
+use transform::Transform;
+use Color;
+use Engine;
+use Error;
+
+// A texture the game can draw into and then use like any other texture, e.g.
+// for minimaps, fading whole scenes or effects applied to the pixels.
+//
+//     let target = RenderTarget::screen_sized(ctx)?;
+//     target.render(ctx, |ctx| scene.render(ctx))?;
+//     target.apply(ctx, &mut Pixelate { block_size: 8 })?;
+//     target.draw_on_screen(ctx, ctx.get_screen_bounds(), &DrawParams::new());
+pub struct RenderTarget {
+    texture: Texture,
+    clear_color: Option<Color>,
+    camera: Option<Transform>,
+}
+
+// An effect applied to the pixels of a render target on the CPU
+pub trait PostPass {
+    fn apply(&mut self, image: &mut Image<RGBA>);
+}
+
+impl RenderTarget {
+    pub fn new(ctx: &mut Engine, width: u32, height: u32) -> Result<RenderTarget, Error> {
+        let texture = ctx.get_texture_registry().create_render_target(width, height)?;
+
+        Ok(RenderTarget {
+            texture,
+            clear_color: Some(Color::RGBA(0, 0, 0, 0)),
+            camera: None,
+        })
+    }
+
+    pub fn screen_sized(ctx: &mut Engine) -> Result<RenderTarget, Error> {
+        let (width, height) = (ctx.get_width(), ctx.get_height());
+        RenderTarget::new(ctx, width, height)
+    }
+
+    // Colour the target is filled with before each render, or None to draw
+    // on top of what was there
+    pub fn with_clear_color(mut self, color: Option<Color>) -> RenderTarget {
+        self.clear_color = color;
+        self
+    }
+
+    // Renders with this camera instead of the main one
+    pub fn with_camera(mut self, camera: Transform) -> RenderTarget {
+        self.camera = Some(camera);
+        self
+    }
+
+    pub fn get_texture(&self) -> &Texture {
+        &self.texture
+    }
+
+    pub fn extent(&self) -> Extent {
+        self.texture.extent()
+    }
+
+    pub fn get_clear_color(&self) -> Option<Color> {
+        self.clear_color
+    }
+
+    pub fn set_clear_color(&mut self, color: Option<Color>) {
+        self.clear_color = color;
+    }
+
+    pub fn get_camera(&self) -> Option<&Transform> {
+        self.camera.as_ref()
+    }
+
+    pub fn set_camera(&mut self, camera: Option<Transform>) {
+        self.camera = camera;
+    }
+
+    // The texture is replaced, so copies of the old one stop being updated
+    pub fn resize(&mut self, ctx: &mut Engine, width: u32, height: u32) -> Result<(), Error> {
+        let extent = self.extent();
+        if extent.width == width as i32 && extent.height == height as i32 {
+            return Ok(());
+        }
+
+        self.texture = ctx.get_texture_registry().create_render_target(width, height)?;
+        Ok(())
+    }
+
+    // Follows the window, e.g. when going full screen
+    pub fn fit_to_screen(&mut self, ctx: &mut Engine) -> Result<(), Error> {
+        let (width, height) = (ctx.get_width(), ctx.get_height());
+        self.resize(ctx, width, height)
+    }
+
+    // Everything drawn through the engine inside `draw` ends up in the target
+    pub fn render<F>(&self, ctx: &mut Engine, draw: F) -> Result<(), Error>
+        where F: FnOnce(&mut Engine)
+    {
+        let clear_color = self.clear_color;
+        let camera = self.camera.clone();
+
+        ctx.with_render_target(
+            &self.texture,
+            |ctx| {
+                if let Some(color) = clear_color {
+                    ctx.clear(color);
+                }
+
+                match camera {
+                    Some(camera) => {
+                        let previous = ctx.get_camera();
+                        ctx.set_camera(camera);
+                        draw(ctx);
+                        ctx.set_camera(previous);
+                    },
+                    None => draw(ctx)
+                }
+            }
+        )
+    }
+
+    pub fn read_pixels(&self, ctx: &mut Engine) -> Result<Image<RGBA>, Error> {
+        ctx.capture_frame(Some(&self.texture))
+    }
+
+    pub fn write_pixels(&self, ctx: &mut Engine, image: &Image<RGBA>) -> Result<(), Error> {
+        ctx.get_texture_registry().update_texture(&self.texture, image)
+    }
+
+    // Reads the pixels back, runs the pass on them and writes them again.
+    // This is slow, so keep the target small or the passes rare.
+    pub fn apply(&self, ctx: &mut Engine, pass: &mut dyn PostPass) -> Result<(), Error> {
+        let mut image = self.read_pixels(ctx)?;
+        pass.apply(&mut image);
+        self.write_pixels(ctx, &image)
+    }
+
+    // Stretches the whole target over `area`, in pixels
+    pub fn draw_on_screen(&self, ctx: &mut Engine, area: Rect2D, params: &DrawParams) {
+        let extent = self.extent();
+        let src = Rect::new(0, 0, extent.width as u32, extent.height as u32);
+
+        let size = area.max - area.min;
+        let dst =
+            Rect::new(
+                area.min.x.round() as i32,
+                area.min.y.round() as i32,
+                size.x.round() as u32,
+                size.y.round() as u32
+            );
+
+        ctx.get_draw_context().copy_ex_with(&self.texture, src, dst, 0.0, params);
+    }
+}
+
+// Replaces blocks of pixels with their average colour. Growing the block size
+// over time makes for a pixelate transition.
+pub struct Pixelate {
+    pub block_size: i32,
+}
+
+impl PostPass for Pixelate {
+    fn apply(&mut self, image: &mut Image<RGBA>) {
+        let block_size = self.block_size;
+        if block_size <= 1 {
+            return;
+        }
+
+        for block_y in (0..image.height()).step_by(block_size as usize) {
+            for block_x in (0..image.width()).step_by(block_size as usize) {
+                let mut sum = [0u32; 4];
+                let mut count = 0;
+
+                for y in block_y..(block_y + block_size) {
+                    for x in block_x..(block_x + block_size) {
+                        if let Some(p) = image.get_pixel(x, y) {
+                            sum[0] += p.r as u32;
+                            sum[1] += p.g as u32;
+                            sum[2] += p.b as u32;
+                            sum[3] += p.a as u32;
+                            count += 1;
+                        }
+                    }
+                }
+
+                let average =
+                    RGBA {
+                        r: (sum[0] / count) as u8,
+                        g: (sum[1] / count) as u8,
+                        b: (sum[2] / count) as u8,
+                        a: (sum[3] / count) as u8,
+                    };
+
+                for y in block_y..(block_y + block_size) {
+                    for x in block_x..(block_x + block_size) {
+                        image.set_pixel(x, y, average);
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Moves every pixel towards a colour, 0.0 leaves the image as it is and 1.0
+// fills it with the colour
+pub struct ColorFade {
+    pub color: Color,
+    pub amount: f32,
+}
+
+impl PostPass for ColorFade {
+    fn apply(&mut self, image: &mut Image<RGBA>) {
+        let f = self.amount.clamp(0.0, 1.0);
+        let mix = |from: u8, to: u8| (from as f32 + (to as f32 - from as f32) * f).round() as u8;
+
+        for p in image.data_mut().iter_mut() {
+            p.r = mix(p.r, self.color.r);
+            p.g = mix(p.g, self.color.g);
+            p.b = mix(p.b, self.color.b);
+        }
+    }
+}
+
+#[test]
+fn test_pixelate_averages_blocks() {
+    let bytes = [
+        0, 0, 0, 255,     100, 0, 0, 255,   50, 50, 50, 255,
+        200, 0, 0, 255,   100, 0, 0, 255,   50, 50, 50, 255,
+    ];
+    let mut image : Image<RGBA> = Image::from_bytes(3, 2, &bytes).unwrap();
+
+    Pixelate { block_size: 2 }.apply(&mut image);
+
+    assert_eq!(image.get_pixel(0, 0), Some(&RGBA { r: 100, g: 0, b: 0, a: 255 }));
+    assert_eq!(image.get_pixel(1, 1), Some(&RGBA { r: 100, g: 0, b: 0, a: 255 }));
+    assert_eq!(image.get_pixel(2, 1), Some(&RGBA { r: 50, g: 50, b: 50, a: 255 }));
+}
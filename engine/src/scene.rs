@@ -1,5 +1,3 @@
-use std::collections::BTreeMap;
-
 use game_object::{
     GameObject,
     GameEvent,
@@ -7,34 +5,83 @@ use game_object::{
     EventReceiver,
     EventType,
     EventMailbox,
-    CollisionShape
+    CollisionShape,
+    SceneCommand
 };
+use event_trace::{EventTrace, EventConsumer};
+use scene_index::{self, SceneIndex};
+use std::any::Any;
+use std::collections::HashMap;
+use scheduler::{Scheduler, TimerId, Sequence, SequenceRunner, SequenceId};
 
 use transform::Transform;
 use ray_shape::RayShape;
 use drawable::DrawContext;
+use render_layer::{RenderLayer, DEFAULT_LAYER};
+use particles::ParticleEmitter;
+use drawable::Drawable;
 use vector::Vec2;
 use rect::Rect2D;
 use Engine;
 use physics::PhysicsSet;
+use generational::{GenerationalArena, GenerationalIndex};
+use as_any::AsAny;
 
-pub type SceneObjectId = i32;
+#[derive(Hash, Eq, PartialEq, Ord, PartialOrd, Clone, Copy, Debug)]
+pub struct SceneObjectId {
+    id: GenerationalIndex,
+}
 
-#[derive(Clone, Copy)]
+#[derive(Hash, Eq, PartialEq, Clone, Copy, Debug)]
 pub struct SceneForceId {
-    id: usize,
+    id: GenerationalIndex,
 }
 
-pub trait Force {
+pub trait Force : AsAny {
     fn calculate_force_on_object(&self, position: Vec2, inv_mass: f32) -> Vec2;
 }
 
+struct SceneForce {
+    force: Box<dyn Force>,
+    enabled: bool,
+}
+
+#[derive(Hash, Eq, PartialEq, Clone, Copy, Debug)]
+pub struct ParticleEmitterId {
+    id: GenerationalIndex,
+}
+
+struct SceneEmitter {
+    emitter: ParticleEmitter,
+    // Object the emitter follows, and where on the object it sits
+    attached_to: Option<(SceneObjectId, Vec2)>,
+    render_layer: String,
+}
+
+// The transform of a child relative to its parent. The object itself keeps
+// its world transform, which is what it renders and collides with, and the
+// scene rewrites it from the parent and `local` on every update.
+struct SceneLink {
+    parent: SceneObjectId,
+    local: Transform,
+    // Where the parent was when the child was last placed
+    parent_world: Transform,
+}
+
 pub struct Scene {
-    objects: BTreeMap<SceneObjectId, Box<dyn GameObject>>,
-    current_id: SceneObjectId,
+    objects: GenerationalArena<Box<dyn GameObject>>,
     event_queue: EventQueue,
     pending_raycasts: Vec<(Vec2, Vec2, SceneObjectId)>,
-    forces: Vec<Box<dyn Force>>,
+    forces: GenerationalArena<SceneForce>,
+    event_budget: usize,
+    event_trace: Option<EventTrace>,
+    frame: u64,
+    links: HashMap<SceneObjectId, SceneLink>,
+    scheduler: Scheduler,
+    sequences: SequenceRunner,
+    render_layers: Vec<RenderLayer>,
+    object_layers: HashMap<SceneObjectId, String>,
+    emitters: GenerationalArena<SceneEmitter>,
 
     // FOR PHYSICS DEBUGGING
     collision_points: Vec<Vec2>,
@@ -53,33 +100,129 @@ impl Default for Scene {
 impl Scene {
     pub fn new() -> Scene {
         Scene {
-            objects: BTreeMap::new(),
-            current_id: 0,
+            objects: GenerationalArena::new(),
             event_queue: EventQueue::new(),
             pending_raycasts: Vec::new(),
-            forces: Vec::new(),
+            forces: GenerationalArena::new(),
+            event_budget: 1000,
+            event_trace: None,
+            frame: 0,
+            links: HashMap::new(),
+            scheduler: Scheduler::new(),
+            sequences: SequenceRunner::new(),
+            render_layers: vec![RenderLayer::new(DEFAULT_LAYER, 0)],
+            object_layers: HashMap::new(),
+            emitters: GenerationalArena::new(),
             collision_points: Vec::new(),
         }
     }
 
     pub fn add_force<T: Force + 'static>(&mut self, force: T) -> SceneForceId {
-        let id = SceneForceId { id: self.forces.len() };
+        let scene_force =
+            SceneForce {
+                force: Box::new(force),
+                enabled: true,
+            };
+
+        SceneForceId { id: self.forces.insert(scene_force) }
+    }
+
+    // Returns false if the force was already removed
+    pub fn remove_force(&mut self, id: SceneForceId) -> bool {
+        self.forces.remove(id.id).is_some()
+    }
+
+    pub fn has_force(&self, id: SceneForceId) -> bool {
+        self.forces.contains(id.id)
+    }
+
+    pub fn set_force_enabled(&mut self, id: SceneForceId, enabled: bool) {
+        if let Some(scene_force) = self.forces.get_mut(id.id) {
+            scene_force.enabled = enabled;
+        }
+    }
+
+    pub fn is_force_enabled(&self, id: SceneForceId) -> bool {
+        self.forces.get(id.id).map(|f| f.enabled).unwrap_or(false)
+    }
+
+    pub fn get_force<T: Force + 'static>(&self, id: SceneForceId) -> Option<&T> {
+        self.forces.get(id.id).and_then(|f| (*f.force).as_any().downcast_ref::<T>())
+    }
+
+    pub fn get_force_mut<T: Force + 'static>(&mut self, id: SceneForceId) -> Option<&mut T> {
+        self.forces.get_mut(id.id).and_then(|f| (*f.force).as_any_mut().downcast_mut::<T>())
+    }
+
+    // Maximum number of events dispatched per update. Whatever is left over
+    // is dispatched on the next update.
+    pub fn set_event_budget(&mut self, budget: usize) {
+        self.event_budget = budget;
+    }
 
-        self.forces.push(Box::new(force));
+    pub fn enable_event_trace(&mut self, capacity: usize) {
+        self.event_trace = Some(EventTrace::new(capacity));
+    }
+
+    pub fn disable_event_trace(&mut self) -> Option<EventTrace> {
+        self.event_trace.take()
+    }
+
+    pub fn get_event_trace(&self) -> Option<&EventTrace> {
+        self.event_trace.as_ref()
+    }
+
+    pub fn submit_event(&mut self, event: EventType, receiver: EventReceiver) {
+        self.event_queue.submit_event(event, receiver);
+    }
+
+    pub fn schedule_event(&mut self, delay: f32, event: EventType, receiver: EventReceiver) -> TimerId {
+        self.scheduler.schedule(delay, event, receiver)
+    }
 
-        id
+    pub fn schedule_repeating_event(&mut self, interval: f32, event: EventType, receiver: EventReceiver) -> TimerId {
+        self.scheduler.schedule_repeating(interval, event, receiver)
     }
 
-    pub fn remove_force(&mut self, id: SceneForceId) {
-        self.forces.remove(id.id);
+    pub fn cancel_timer(&mut self, id: TimerId) -> bool {
+        self.scheduler.cancel(id)
+    }
+
+    pub fn is_timer_pending(&self, id: TimerId) -> bool {
+        self.scheduler.is_pending(id)
+    }
+
+    pub fn run_sequence(&mut self, sequence: Sequence) -> SequenceId {
+        self.sequences.start(sequence)
+    }
+
+    pub fn cancel_sequence(&mut self, id: SequenceId) -> bool {
+        self.sequences.cancel(id)
+    }
+
+    pub fn is_sequence_running(&self, id: SequenceId) -> bool {
+        self.sequences.is_running(id)
+    }
+
+    fn run_sequences(&mut self, engine: &mut Engine, dt: f32) {
+        for id in self.sequences.ids() {
+            if let Some(mut sequence) = self.sequences.take(id) {
+                sequence.step(self, engine, dt);
+                self.sequences.put_back(id, sequence);
+            }
+        }
     }
 
     pub fn get(&self, id: SceneObjectId) -> Option<&dyn GameObject> {
-        self.objects.get(&id).map(|boxed| boxed.as_ref())
+        self.objects.get(id.id).map(|boxed| boxed.as_ref())
     }
 
     pub fn get_mut(&mut self, id: SceneObjectId) -> Option<&mut Box<dyn GameObject>> {
-        self.objects.get_mut(&id)
+        self.objects.get_mut(id.id)
+    }
+
+    pub fn contains(&self, id: SceneObjectId) -> bool {
+        self.objects.contains(id.id)
     }
 
     pub fn dispatch_nearest_event(
@@ -88,12 +231,14 @@ impl Scene {
         max_distance: Option<f32>,
         event: EventType,
         sender: Option<SceneObjectId>
-    ) {
+    ) -> Vec<SceneObjectId> {
         use std::f32;
 
-        let mut objects_with_distance : Vec<(f32, &mut Box<dyn GameObject>)> =
+        let mut consumers = Vec::new();
+
+        let mut objects_with_distance : Vec<(f32, SceneObjectId, &mut Box<dyn GameObject>)> =
             self.objects.iter_mut().map(
-                |(_id, ob)| {
+                |(id, ob)| {
                     let distance =
                         if let Some(pob) = ob.get_physical_object() {
                             let position = pob.get_transform().get_translation();
@@ -102,15 +247,15 @@ impl Scene {
                             f32::MAX
                         };
 
-                    (distance, ob)
+                    (distance, SceneObjectId { id }, ob)
                 }
             ).collect();
 
-        objects_with_distance.sort_by(|(d_a, _o_a), (d_b, _o_b)| d_a.partial_cmp(d_b).unwrap());
+        objects_with_distance.sort_by(|(d_a, _, _o_a), (d_b, _, _o_b)| d_a.partial_cmp(d_b).unwrap());
 
         let it = objects_with_distance.iter_mut();
 
-        for (distance, object) in it {
+        for (distance, id, object) in it {
             if *distance > max_distance.unwrap_or(f32::MAX) {
                 println!("Event lost because max distance was reached: distance={}", distance);
                 break;
@@ -118,9 +263,12 @@ impl Scene {
 
             if object.on_event(event.clone(), sender) {
                 // Object handlet the event.
+                consumers.push(*id);
                 break;
             }
         }
+
+        consumers
     }
 
     pub fn dispatch_nearby_event(
@@ -129,12 +277,14 @@ impl Scene {
         max_distance: Option<f32>,
         event: EventType,
         sender: Option<SceneObjectId>
-    ) {
+    ) -> Vec<SceneObjectId> {
         use std::f32;
 
-        let mut objects_with_distance : Vec<(f32, &mut Box<dyn GameObject>)> =
+        let mut consumers = Vec::new();
+
+        let mut objects_with_distance : Vec<(f32, SceneObjectId, &mut Box<dyn GameObject>)> =
             self.objects.iter_mut().map(
-                |(_id, ob)| {
+                |(id, ob)| {
                     let distance =
                         if let Some(pob) = ob.get_physical_object() {
                             let position = pob.get_transform().get_translation();
@@ -143,28 +293,38 @@ impl Scene {
                             f32::MAX
                         };
 
-                    (distance, ob)
+                    (distance, SceneObjectId { id }, ob)
                 }
             ).collect();
 
-        objects_with_distance.sort_by(|(d_a, _o_a), (d_b, _o_b)| d_a.partial_cmp(d_b).unwrap());
+        objects_with_distance.sort_by(|(d_a, _, _o_a), (d_b, _, _o_b)| d_a.partial_cmp(d_b).unwrap());
 
         let it = objects_with_distance.iter_mut();
 
-        for (distance, object) in it {
+        for (distance, id, object) in it {
             if *distance > max_distance.unwrap_or(f32::MAX) {
                 println!("Event lost because max distance was reached: distance={}", distance);
                 break;
             }
 
-            object.on_event(event.clone(), sender);
+            if object.on_event(event.clone(), sender) {
+                consumers.push(*id);
+            }
         }
+
+        consumers
     }
 
-    pub fn broadcast_event(&mut self, event: EventType, sender: Option<SceneObjectId>) {
-        for (_id, object) in self.objects.iter_mut() {
-            object.on_event(event.clone(), sender);
+    pub fn broadcast_event(&mut self, event: EventType, sender: Option<SceneObjectId>) -> Vec<SceneObjectId> {
+        let mut consumers = Vec::new();
+
+        for (id, object) in self.objects.iter_mut() {
+            if object.on_event(event.clone(), sender) {
+                consumers.push(SceneObjectId { id });
+            }
         }
+
+        consumers
     }
 
     pub fn do_level_collision(&mut self, collider: &dyn LevelCollider) {
@@ -230,10 +390,10 @@ impl Scene {
     pub fn update(&mut self, engine: &mut Engine, collider: Option<&dyn LevelCollider>, dt: f32) -> Vec<GameEvent> {
         for (_, o) in self.objects.iter_mut( ) {
             if let Some(po) = o.get_physical_object_mut() {
-                for f in self.forces.iter() {
+                for (_, f) in self.forces.iter().filter(|(_, f)| f.enabled) {
                     let position = po.get_transform().get_translation();
                     let inv_mass = po.get_inv_mass();
-                    let force = f.calculate_force_on_object(position, inv_mass);
+                    let force = f.force.calculate_force_on_object(position, inv_mass);
 
                     let acceleration = force * inv_mass;
 
@@ -276,7 +436,7 @@ impl Scene {
                     for axis in physics_set.get_collision_axes_for_body(id) {
                         self.event_queue.submit_event(
                             EventType::Collide { force: axis },
-                            EventReceiver::Addressed { object_id: *ob_id }
+                            EventReceiver::Addressed { object_id: SceneObjectId { id: ob_id } }
                         );
                     }
                 }
@@ -287,8 +447,15 @@ impl Scene {
             self.do_level_collision(level_collider);
         }
 
+        let scene_index = self.build_index();
+
         for (id, object) in self.objects.iter_mut() {
-            object.update(engine, &mut self.event_queue.bind_to_sender(*id), dt);
+            let mut mailbox =
+                self.event_queue
+                    .bind_to_sender(SceneObjectId { id })
+                    .with_scene_index(&scene_index);
+
+            object.update(engine, &mut mailbox, dt);
         }
 
         for (_, o) in self.objects.iter_mut( ) {
@@ -302,49 +469,124 @@ impl Scene {
                 }
             }
         }
+
+        self.sync_hierarchy();
+        self.update_emitters(dt);
+
+        self.scheduler.update(dt, &mut self.event_queue);
+        self.run_sequences(engine, dt);
+
+        let events_for_parent = self.dispatch_events();
+
+        self.frame += 1;
+
+        self.apply_commands();
+
+        events_for_parent
+    }
+
+    // Dispatches queued events by priority, at most the event budget. The
+    // rest stays queued and competes with newer events on the next update,
+    // so a backlog of low priority events can be overtaken.
+    fn dispatch_events(&mut self) -> Vec<GameEvent> {
         let mut events_for_parent = Vec::new();
 
-        while let Some(event) = self.event_queue.poll() {
-            match event.receiver {
-                EventReceiver::Nearest { origin, max_distance } => {
-                    self.dispatch_nearest_event(origin, max_distance, event.event_type, event.sender)
-                },
-                EventReceiver::Broadcast => {
-                    self.broadcast_event(event.event_type, event.sender)
+        for _ in 0..self.event_budget {
+            let event = match self.event_queue.poll() {
+                Some(event) => event,
+                None => break
+            };
+
+            let sender = event.sender;
+            let receiver = event.receiver.kind();
+            let name = event.event_type.name();
+
+            let consumers = self.dispatch_event(event, &mut events_for_parent);
+
+            if let Some(ref mut trace) = self.event_trace {
+                trace.record(self.frame, sender, receiver, name, consumers);
+            }
+        }
+
+        events_for_parent
+    }
+
+    fn dispatch_event(&mut self, event: GameEvent, events_for_parent: &mut Vec<GameEvent>) -> Vec<EventConsumer> {
+        let to_consumers = |ids: Vec<SceneObjectId>| ids.into_iter().map(EventConsumer::Object).collect();
+
+        match event.receiver {
+            EventReceiver::Nearest { origin, max_distance } => {
+                to_consumers(self.dispatch_nearest_event(origin, max_distance, event.event_type, event.sender))
+            },
+            EventReceiver::Broadcast => {
+                to_consumers(self.broadcast_event(event.event_type, event.sender))
+            },
+            EventReceiver::Scene => {
+                if self.handle_scene_event(event.event_type.clone(), event.sender) {
+                    vec![EventConsumer::Scene]
+                } else {
+                    events_for_parent.push(event);
+                    vec![EventConsumer::Parent]
+                }
+            },
+            EventReceiver::Addressed { object_id } => {
+                let handled =
+                    self.objects
+                        .get_mut(object_id.id)
+                        .map(|object| object.on_event(event.event_type, event.sender))
+                        .unwrap_or(false);
+
+                if handled { vec![EventConsumer::Object(object_id)] } else { Vec::new() }
+            },
+            EventReceiver::Nearby { origin, max_distance } => {
+                to_consumers(self.dispatch_nearby_event(origin, max_distance, event.event_type, event.sender))
+            }
+        }
+    }
+
+    // Commands are deferred until every object has been updated and every
+    // event dispatched, so objects never see the scene change under them.
+    fn apply_commands(&mut self) {
+        while let Some((command, sender)) = self.event_queue.poll_command() {
+            match command {
+                SceneCommand::SpawnObject { object } => {
+                    self.add_boxed_object(object);
                 },
-                EventReceiver::Scene => {
-                    if !self.handle_scene_event(event.event_type.clone(), event.sender) {
-                        events_for_parent.push(event);
+                SceneCommand::ScheduleEvent { delay, event, receiver } => {
+                    match sender {
+                        Some(owner) => { self.scheduler.schedule_for(owner, delay, event, receiver); },
+                        None => { self.scheduler.schedule(delay, event, receiver); }
                     }
                 },
-                EventReceiver::Addressed { object_id } => {
-                    if self.objects.contains_key(&object_id) {
-                        self.objects.get_mut(&object_id).unwrap().on_event(event.event_type, event.sender);
+                SceneCommand::CancelScheduledEvents => {
+                    if let Some(owner) = sender {
+                        self.scheduler.cancel_owned_by(owner);
                     }
-                },
-                EventReceiver::Nearby { origin, max_distance } => {
-                    self.dispatch_nearby_event(origin, max_distance, event.event_type, event.sender)
                 }
             }
         }
-        events_for_parent
     }
 
+    // Draws once per enabled viewport, or to the whole screen with the main
+    // camera if there are none
     pub fn render(&self, engine: &mut Engine) {
         let screen_bounds = engine.get_screen_bounds();
 
-        let mut ctx =
-            DrawContext::new(
-                engine.canvas,
-                &engine.texture_registry,
-                &engine.camera,
-                screen_bounds
-            );
+        if !engine.has_viewports() {
+            let camera = engine.get_camera();
+            self.render_with_camera(engine, &camera, None);
+        }
 
-        let mut v :Vec<_> = self.objects.iter().collect();
-        v.sort_by(|(_, a), (_, b)| a.get_z_index().cmp(&b.get_z_index()));
-        for (_id, object) in v {
-            object.render(&mut ctx);
+        for name in engine.get_viewport_names() {
+            let (camera, rect) =
+                match engine.get_viewport(&name) {
+                    Some(viewport) if viewport.is_enabled() => {
+                        (viewport.get_camera().clone(), viewport.get_screen_rect(screen_bounds))
+                    },
+                    _ => { continue; }
+                };
+
+            self.render_with_camera(engine, &camera, Some(rect));
         }
 
         /*
@@ -359,18 +601,428 @@ impl Scene {
         */
     }
 
+    fn render_with_camera(&self, engine: &mut Engine, camera: &Transform, viewport: Option<Rect2D>) {
+        let screen_bounds = engine.get_screen_bounds();
+        let window_scale = engine.get_window_scale();
+
+        for layer in self.render_layers.iter().filter(|l| l.is_visible()) {
+            let layer_camera = layer.apply_to_camera(camera, window_scale);
+
+            let mut ctx =
+                match viewport {
+                    Some(rect) => DrawContext::in_viewport(engine.canvas, &engine.texture_registry, &layer_camera, rect),
+                    None => DrawContext::new(engine.canvas, &engine.texture_registry, &layer_camera, screen_bounds)
+                };
+
+            let mut v :Vec<_> =
+                self.objects.iter()
+                    .filter(|(id, _)| self.get_render_layer(SceneObjectId { id: *id }) == layer.get_name())
+                    .collect();
+
+            v.sort_by_key(|(_, o)| o.get_z_index());
+            for (_id, object) in v {
+                object.render(&mut ctx);
+            }
+
+            for (_, e) in self.emitters.iter().filter(|(_, e)| e.render_layer == layer.get_name()) {
+                e.emitter.draw(&mut ctx);
+            }
+        }
+    }
+
+    // The scene updates the emitter and removes it once it is finished,
+    // see ParticleEmitter::is_finished
+    pub fn add_emitter(&mut self, emitter: ParticleEmitter) -> ParticleEmitterId {
+        let scene_emitter =
+            SceneEmitter {
+                emitter,
+                attached_to: None,
+                render_layer: DEFAULT_LAYER.to_string(),
+            };
+
+        ParticleEmitterId { id: self.emitters.insert(scene_emitter) }
+    }
+
+    pub fn remove_emitter(&mut self, id: ParticleEmitterId) -> bool {
+        self.emitters.remove(id.id).is_some()
+    }
+
+    pub fn has_emitter(&self, id: ParticleEmitterId) -> bool {
+        self.emitters.contains(id.id)
+    }
+
+    pub fn get_emitter(&self, id: ParticleEmitterId) -> Option<&ParticleEmitter> {
+        self.emitters.get(id.id).map(|e| &e.emitter)
+    }
+
+    pub fn get_emitter_mut(&mut self, id: ParticleEmitterId) -> Option<&mut ParticleEmitter> {
+        self.emitters.get_mut(id.id).map(|e| &mut e.emitter)
+    }
+
+    // Moves the emitter along with the object. When the object is removed
+    // the emitter stops, and goes away once its particles have died.
+    pub fn attach_emitter(&mut self, id: ParticleEmitterId, object: SceneObjectId, offset: Vec2) -> bool {
+        if !self.contains(object) {
+            return false;
+        }
+
+        match self.emitters.get_mut(id.id) {
+            Some(e) => {
+                e.attached_to = Some((object, offset));
+                true
+            },
+            None => false
+        }
+    }
+
+    pub fn detach_emitter(&mut self, id: ParticleEmitterId) {
+        if let Some(e) = self.emitters.get_mut(id.id) {
+            e.attached_to = None;
+        }
+    }
+
+    pub fn set_emitter_layer(&mut self, id: ParticleEmitterId, name: &str) -> bool {
+        if self.get_render_layer_settings(name).is_none() {
+            return false;
+        }
+
+        match self.emitters.get_mut(id.id) {
+            Some(e) => {
+                e.render_layer = name.to_string();
+                true
+            },
+            None => false
+        }
+    }
+
+    fn update_emitters(&mut self, dt: f32) {
+        let objects = &self.objects;
+        let forces = &self.forces;
+
+        for (_, e) in self.emitters.iter_mut() {
+            if let Some((object, offset)) = e.attached_to {
+                let position =
+                    objects.get(object.id)
+                        .and_then(|o| o.get_physical_object())
+                        .map(|po| po.get_transform().get_translation());
+
+                match position {
+                    Some(position) => { e.emitter.set_position(position + offset); },
+                    None => {
+                        e.attached_to = None;
+                        e.emitter.stop();
+                    }
+                }
+            }
+
+            // Particles have no mass of their own, so forces are applied as
+            // if it was one
+            e.emitter.update_with_forces(
+                dt,
+                |position| {
+                    let mut acceleration = Vec2::new();
+                    for (_, f) in forces.iter().filter(|(_, f)| f.enabled) {
+                        acceleration += f.force.calculate_force_on_object(position, 1.0);
+                    }
+                    acceleration
+                }
+            );
+        }
+
+        let finished : Vec<_> =
+            self.emitters.iter()
+                .filter(|(_, e)| e.emitter.is_finished())
+                .map(|(id, _)| id)
+                .collect();
+
+        for id in finished {
+            self.emitters.remove(id);
+        }
+    }
+
+    // Draws every object, ignoring layers
+    pub fn render_objects(&self, ctx: &mut DrawContext) {
+        let mut v :Vec<_> = self.objects.iter().collect();
+        v.sort_by(|(_, a), (_, b)| a.get_z_index().cmp(&b.get_z_index()));
+        for (_id, object) in v {
+            object.render(ctx);
+        }
+    }
+
+    // Replaces a layer with the same name
+    pub fn add_render_layer(&mut self, layer: RenderLayer) {
+        self.render_layers.retain(|l| l.get_name() != layer.get_name());
+        self.render_layers.push(layer);
+        self.render_layers.sort_by_key(|l| l.get_order());
+    }
+
+    // Objects on the removed layer go back to the default layer, which
+    // cannot be removed
+    pub fn remove_render_layer(&mut self, name: &str) -> bool {
+        if name == DEFAULT_LAYER {
+            return false;
+        }
+
+        let count = self.render_layers.len();
+        self.render_layers.retain(|l| l.get_name() != name);
+        self.object_layers.retain(|_, layer| layer != name);
+
+        for (_, e) in self.emitters.iter_mut().filter(|(_, e)| e.render_layer == name) {
+            e.render_layer = DEFAULT_LAYER.to_string();
+        }
+
+        self.render_layers.len() != count
+    }
+
+    pub fn get_render_layer_settings(&self, name: &str) -> Option<&RenderLayer> {
+        self.render_layers.iter().find(|l| l.get_name() == name)
+    }
+
+    pub fn get_render_layer_settings_mut(&mut self, name: &str) -> Option<&mut RenderLayer> {
+        self.render_layers.iter_mut().find(|l| l.get_name() == name)
+    }
+
+    pub fn set_render_layer_visible(&mut self, name: &str, visible: bool) {
+        if let Some(layer) = self.get_render_layer_settings_mut(name) {
+            layer.set_visible(visible);
+        }
+    }
+
+    // For debugging, e.g. bound to a key to hide the background
+    pub fn toggle_render_layer(&mut self, name: &str) {
+        if let Some(layer) = self.get_render_layer_settings_mut(name) {
+            let visible = layer.is_visible();
+            layer.set_visible(!visible);
+        }
+    }
+
+    // Returns false if there is no such object or layer
+    pub fn set_render_layer(&mut self, id: SceneObjectId, name: &str) -> bool {
+        if !self.contains(id) || self.get_render_layer_settings(name).is_none() {
+            return false;
+        }
+
+        if name == DEFAULT_LAYER {
+            self.object_layers.remove(&id);
+        } else {
+            self.object_layers.insert(id, name.to_string());
+        }
+
+        true
+    }
+
+    pub fn get_render_layer(&self, id: SceneObjectId) -> &str {
+        self.object_layers.get(&id).map(|l| l.as_str()).unwrap_or(DEFAULT_LAYER)
+    }
+
     pub fn add_object<T: GameObject>(&mut self, object: T) -> SceneObjectId {
-        let new_id = self.current_id;
-        self.current_id += 1;
-        self.objects.insert(new_id, Box::new(object));
-        new_id
+        self.add_boxed_object(Box::new(object))
+    }
+
+    pub fn add_boxed_object(&mut self, object: Box<dyn GameObject>) -> SceneObjectId {
+        SceneObjectId { id: self.objects.insert(object) }
     }
 
     pub fn remove_object(&mut self, object_id: SceneObjectId){
         println!("Attempting to delete object");
-        if self.objects.contains_key(&object_id) {
-            self.objects.remove(&object_id);
+
+        for child in self.get_children(object_id) {
+            self.remove_object(child);
         }
+
+        self.links.remove(&object_id);
+        self.object_layers.remove(&object_id);
+        self.objects.remove(object_id.id);
+    }
+
+    pub fn get_world_transform(&self, id: SceneObjectId) -> Option<Transform> {
+        self.get(id).and_then(|o| o.get_scene_transform()).cloned()
+    }
+
+    fn set_world_transform(&mut self, id: SceneObjectId, transform: Transform) {
+        if let Some(t) = self.get_mut(id).and_then(|o| o.get_scene_transform_mut()) {
+            *t = transform;
+        }
+    }
+
+    // Attaches child to parent, or moves it to a new parent, without moving
+    // it in the world. Both objects need a scene transform, see
+    // GameObject::get_scene_transform.
+    pub fn set_parent(&mut self, child: SceneObjectId, parent: SceneObjectId) -> bool {
+        if child == parent || self.is_ancestor(child, parent) {
+            return false;
+        }
+
+        let (parent_world, child_world) =
+            match (self.get_world_transform(parent), self.get_world_transform(child)) {
+                (Some(p), Some(c)) => (p, c),
+                _ => return false
+            };
+
+        let local = parent_world.inverse().combine(&child_world);
+        self.links.insert(child, SceneLink { parent, local, parent_world });
+        true
+    }
+
+    // The child stays where it is in the world
+    pub fn detach(&mut self, child: SceneObjectId) {
+        self.links.remove(&child);
+    }
+
+    pub fn get_parent(&self, child: SceneObjectId) -> Option<SceneObjectId> {
+        self.links.get(&child).map(|link| link.parent)
+    }
+
+    pub fn get_children(&self, parent: SceneObjectId) -> Vec<SceneObjectId> {
+        let mut children : Vec<SceneObjectId> =
+            self.links.iter()
+                .filter(|(_, link)| link.parent == parent)
+                .map(|(child, _)| *child)
+                .collect();
+
+        children.sort();
+        children
+    }
+
+    fn is_ancestor(&self, ancestor: SceneObjectId, id: SceneObjectId) -> bool {
+        let mut current = self.get_parent(id);
+        while let Some(p) = current {
+            if p == ancestor {
+                return true;
+            }
+            current = self.get_parent(p);
+        }
+        false
+    }
+
+    fn get_depth(&self, id: SceneObjectId) -> usize {
+        let mut depth = 0;
+        let mut current = self.get_parent(id);
+        while let Some(p) = current {
+            depth += 1;
+            current = self.get_parent(p);
+        }
+        depth
+    }
+
+    // Transform relative to the parent, or the world transform if the object
+    // has no parent
+    pub fn get_local_transform(&self, id: SceneObjectId) -> Option<Transform> {
+        let world = self.get_world_transform(id)?;
+
+        match self.links.get(&id) {
+            Some(link) => Some(Scene::current_local(link, &world)),
+            None => Some(world)
+        }
+    }
+
+    pub fn set_local_transform(&mut self, id: SceneObjectId, local: Transform) {
+        let parent_world =
+            self.get_parent(id).and_then(|parent| self.get_world_transform(parent));
+
+        match parent_world {
+            Some(parent_world) => {
+                self.set_world_transform(id, parent_world.combine(&local));
+                if let Some(link) = self.links.get_mut(&id) {
+                    link.local = local;
+                    link.parent_world = parent_world;
+                }
+            },
+            None => self.set_world_transform(id, local)
+        }
+    }
+
+    // The stored local transform, unless the child moved itself since it was
+    // last placed. Then the move counts as a change to its local transform.
+    fn current_local(link: &SceneLink, world: &Transform) -> Transform {
+        if link.parent_world.combine(&link.local) == *world {
+            link.local.clone()
+        } else {
+            link.parent_world.inverse().combine(world)
+        }
+    }
+
+    // Places every child at its local transform from its parent
+    fn sync_hierarchy(&mut self) {
+        let mut children : Vec<(usize, SceneObjectId)> =
+            self.links.keys().map(|id| (self.get_depth(*id), *id)).collect();
+
+        // Parents have to be in place before their children are moved
+        children.sort();
+
+        for (_, child) in children {
+            let parent =
+                match self.links.get(&child) {
+                    Some(link) => link.parent,
+                    None => continue
+                };
+
+            let (parent_world, child_world) =
+                match (self.get_world_transform(parent), self.get_world_transform(child)) {
+                    (Some(p), Some(c)) => (p, c),
+                    _ => {
+                        self.links.remove(&child);
+                        continue;
+                    }
+                };
+
+            let link = self.links.get_mut(&child).unwrap();
+            link.local = Scene::current_local(link, &child_world);
+            link.parent_world = parent_world;
+
+            let world = link.parent_world.combine(&link.local);
+            self.set_world_transform(child, world);
+        }
+    }
+
+    pub fn build_index(&self) -> SceneIndex {
+        SceneIndex::build(self.objects.iter().map(|(id, o)| (SceneObjectId { id }, o.as_ref())))
+    }
+
+    pub fn get_position(&self, id: SceneObjectId) -> Option<Vec2> {
+        self.get(id)
+            .and_then(|o| o.get_physical_object())
+            .map(|po| po.get_transform().get_translation())
+    }
+
+    pub fn has_tag(&self, id: SceneObjectId, tag: &str) -> bool {
+        self.get(id).map(|o| o.get_tags().contains(&tag)).unwrap_or(false)
+    }
+
+    pub fn find_by_tag(&self, tag: &str) -> Vec<SceneObjectId> {
+        self.objects.iter()
+            .filter(|(_, o)| o.get_tags().contains(&tag))
+            .map(|(id, _)| SceneObjectId { id })
+            .collect()
+    }
+
+    pub fn nearest_with_tag(&self, position: Vec2, tag: &str) -> Option<(SceneObjectId, Vec2)> {
+        scene_index::nearest(
+            self.find_by_tag(tag)
+                .into_iter()
+                .filter_map(|id| self.get_position(id).map(|p| (id, p))),
+            position
+        )
+    }
+
+    pub fn get_as<T: Any>(&self, id: SceneObjectId) -> Option<&T> {
+        self.get(id).and_then(|o| o.as_any().downcast_ref::<T>())
+    }
+
+    pub fn get_as_mut<T: Any>(&mut self, id: SceneObjectId) -> Option<&mut T> {
+        self.objects.get_mut(id.id).and_then(|o| (**o).as_any_mut().downcast_mut::<T>())
+    }
+
+    pub fn iter_of_type<T: Any>(&self) -> impl Iterator<Item=(SceneObjectId, &T)> {
+        self.objects.iter().filter_map(
+            |(id, o)| (**o).as_any().downcast_ref::<T>().map(|t| (SceneObjectId { id }, t))
+        )
+    }
+
+    pub fn iter_of_type_mut<T: Any>(&mut self) -> impl Iterator<Item=(SceneObjectId, &mut T)> {
+        self.objects.iter_mut().filter_map(
+            |(id, o)| (**o).as_any_mut().downcast_mut::<T>().map(|t| (SceneObjectId { id }, t))
+        )
     }
 
     pub fn get_objects_in_rect(&self, rect: Rect2D) -> Vec<&dyn GameObject> {
@@ -391,3 +1043,196 @@ impl Scene {
         result
     }
 }
+
+#[cfg(test)]
+struct Marker {
+    transform: Transform,
+}
+
+#[cfg(test)]
+impl Marker {
+    fn at(x: f32, y: f32) -> Marker {
+        let mut transform = Transform::new();
+        transform.set_translation(Vec2::from_coords(x, y));
+        Marker { transform }
+    }
+}
+
+#[cfg(test)]
+impl GameObject for Marker {
+    fn update(&mut self, _ctx: &mut Engine, _event_mailbox: &mut dyn EventMailbox, _dt: f32) -> bool { true }
+
+    fn render(&self, _ctx: &mut DrawContext) {}
+
+    fn get_scene_transform(&self) -> Option<&Transform> { Some(&self.transform) }
+
+    fn get_scene_transform_mut(&mut self) -> Option<&mut Transform> { Some(&mut self.transform) }
+}
+
+#[test]
+fn test_scene_hierarchy() {
+    let mut scene = Scene::new();
+
+    let root = scene.add_object(Marker::at(100.0, 0.0));
+    let other = scene.add_object(Marker::at(0.0, 50.0));
+    let child = scene.add_object(Marker::at(110.0, 0.0));
+    let grandchild = scene.add_object(Marker::at(110.0, 20.0));
+
+    assert!(scene.set_parent(child, root));
+    assert!(scene.set_parent(grandchild, child));
+    assert!(!scene.set_parent(root, grandchild));
+
+    let local = scene.get_local_transform(child).unwrap();
+    assert!((local.get_translation() - Vec2::from_coords(10.0, 0.0)).len() < 0.001);
+
+    // Children follow their parent, scaled and rotated with it
+    let mut transform = Transform::new();
+    transform.set_translation(Vec2::from_coords(200.0, 0.0));
+    transform.set_scale(2.0);
+    scene.set_world_transform(root, transform);
+    scene.sync_hierarchy();
+
+    let world = scene.get_world_transform(grandchild).unwrap();
+    assert!((world.get_translation() - Vec2::from_coords(220.0, 40.0)).len() < 0.001);
+
+    // A child moving itself changes its local transform
+    let mut moved = scene.get_world_transform(child).unwrap();
+    moved.translate(Vec2::from_coords(0.0, 10.0));
+    scene.set_world_transform(child, moved);
+    scene.sync_hierarchy();
+
+    let local = scene.get_local_transform(child).unwrap();
+    assert!((local.get_translation() - Vec2::from_coords(10.0, 5.0)).len() < 0.001);
+
+    let mut local = local;
+    local.set_translation(Vec2::from_coords(0.0, 10.0));
+    scene.set_local_transform(child, local);
+    scene.sync_hierarchy();
+
+    let world = scene.get_world_transform(child).unwrap();
+    assert!((world.get_translation() - Vec2::from_coords(200.0, 20.0)).len() < 0.001);
+
+    // Reparenting keeps the world position
+    let before = scene.get_world_transform(child).unwrap();
+    assert!(scene.set_parent(child, other));
+    scene.sync_hierarchy();
+
+    let after = scene.get_world_transform(child).unwrap();
+    assert!((after.get_translation() - before.get_translation()).len() < 0.001);
+    assert_eq!(scene.get_children(other), vec![child]);
+    assert!(scene.get_children(root).is_empty());
+
+    // Removing a parent removes its children with it
+    scene.remove_object(other);
+    assert!(scene.get(child).is_none());
+    assert!(scene.get(grandchild).is_none());
+    assert!(scene.get(root).is_some());
+}
+
+#[cfg(test)]
+fn probe(hint: &str) -> EventType {
+    EventType::Probe { hint: hint.to_string() }
+}
+
+#[cfg(test)]
+fn probe_hints(events: Vec<GameEvent>) -> Vec<String> {
+    events.into_iter()
+        .filter_map(|event| match event.event_type {
+            EventType::Probe { hint } => Some(hint),
+            _ => None
+        })
+        .collect()
+}
+
+#[test]
+fn test_event_dispatch_order() {
+    use game_object::EventPriority;
+
+    let mut scene = Scene::new();
+    scene.enable_event_trace(10);
+    scene.set_event_budget(3);
+
+    scene.submit_event(probe("first"), EventReceiver::Scene);
+    scene.submit_event(probe("second"), EventReceiver::Scene);
+    scene.event_queue.submit_event_with_priority(probe("urgent"), EventReceiver::Scene, EventPriority::High);
+    scene.event_queue.submit_event_with_priority(probe("whenever"), EventReceiver::Scene, EventPriority::Low);
+    scene.submit_event(probe("third"), EventReceiver::Scene);
+
+    // Higher priorities first, otherwise in the order submitted
+    assert_eq!(probe_hints(scene.dispatch_events()), vec!["urgent", "first", "second"]);
+
+    // What did not fit in the budget is dispatched next time, after anything
+    // more urgent
+    scene.event_queue.submit_event_with_priority(probe("late"), EventReceiver::Scene, EventPriority::High);
+    assert_eq!(probe_hints(scene.dispatch_events()), vec!["late", "third", "whenever"]);
+    assert!(scene.event_queue.is_empty());
+
+    let marker = scene.add_object(Marker::at(0.0, 0.0));
+    scene.submit_event(EventType::Interact, EventReceiver::Addressed { object_id: marker });
+    assert!(scene.dispatch_events().is_empty());
+
+    let trace: Vec<_> = scene.get_event_trace().unwrap().entries().collect();
+    assert_eq!(trace.len(), 7);
+    assert_eq!(trace[0].event, "Probe");
+    assert_eq!(trace[0].receiver, "Scene");
+    assert_eq!(trace[0].consumers, vec![EventConsumer::Parent]);
+    assert_eq!(trace[6].event, "Interact");
+    assert_eq!(trace[6].receiver, "Addressed");
+    assert!(trace[6].consumers.is_empty());
+}
+
+#[test]
+fn test_spawned_objects_wait_for_commands() {
+    let mut scene = Scene::new();
+
+    let first = scene.add_object(Marker::at(0.0, 0.0));
+    let second = scene.add_object(Marker::at(10.0, 0.0));
+    let third = scene.add_object(Marker::at(20.0, 0.0));
+    scene.remove_object(second);
+
+    {
+        let mut mailbox = scene.event_queue.bind_to_sender(first);
+        let mailbox: &mut dyn EventMailbox = &mut mailbox;
+        mailbox.spawn_object(Marker::at(30.0, 0.0));
+    }
+    assert_eq!(scene.objects.len(), 2);
+
+    scene.apply_commands();
+    assert_eq!(scene.objects.len(), 3);
+    assert!(scene.get(second).is_none());
+
+    // Objects are updated in slot order, so the new object takes the place
+    // of the removed one
+    let order: Vec<_> = scene.objects.iter().map(|(id, _)| SceneObjectId { id }).collect();
+    assert_eq!(order[0], first);
+    assert_eq!(order[2], third);
+    let spawned = scene.get_world_transform(order[1]).unwrap();
+    assert_eq!(spawned.get_translation(), Vec2::from_coords(30.0, 0.0));
+}
+
+#[test]
+fn test_objects_cancel_their_scheduled_events() {
+    let mut scene = Scene::new();
+
+    let marker = scene.add_object(Marker::at(0.0, 0.0));
+    let other = scene.add_object(Marker::at(10.0, 0.0));
+
+    for &sender in [marker, marker, other].iter() {
+        let mut mailbox = scene.event_queue.bind_to_sender(sender);
+        let mailbox: &mut dyn EventMailbox = &mut mailbox;
+        mailbox.schedule_event(0.5, EventType::Interact, EventReceiver::Addressed { object_id: sender });
+    }
+    scene.apply_commands();
+
+    {
+        let mut mailbox = scene.event_queue.bind_to_sender(marker);
+        let mailbox: &mut dyn EventMailbox = &mut mailbox;
+        assert_eq!(mailbox.get_sender_id(), Some(marker));
+        mailbox.cancel_scheduled_events();
+    }
+    scene.apply_commands();
+
+    // Only the other object's event is left
+    scene.scheduler.update(1.0, &mut scene.event_queue);
+    assert_eq!(scene.event_queue.len(), 1);
+}
@@ -0,0 +1,168 @@
+use std::any::{Any, TypeId};
+
+use game_object::GameObject;
+use scene::SceneObjectId;
+use vector::Vec2;
+
+#[cfg(test)]
+use game_object::{EventMailbox, PhysicalObject};
+#[cfg(test)]
+use scene::Scene;
+#[cfg(test)]
+use transform::Transform;
+#[cfg(test)]
+use drawable::DrawContext;
+#[cfg(test)]
+use Engine;
+
+pub struct IndexEntry {
+    pub id: SceneObjectId,
+    pub tags: Vec<&'static str>,
+    pub position: Option<Vec2>,
+    type_id: TypeId,
+}
+
+// Snapshot of which objects are in the scene, taken right before objects are
+// updated. Objects cannot borrow each other during update, so this is what
+// they look each other up in.
+pub struct SceneIndex {
+    entries: Vec<IndexEntry>,
+}
+
+impl SceneIndex {
+    pub fn build<'a, I>(objects: I) -> SceneIndex
+        where I: Iterator<Item=(SceneObjectId, &'a dyn GameObject)>
+    {
+        let entries =
+            objects.map(
+                |(id, object)| {
+                    IndexEntry {
+                        id,
+                        tags: object.get_tags().to_vec(),
+                        position: object.get_physical_object().map(|po| po.get_transform().get_translation()),
+                        type_id: object.as_any().type_id(),
+                    }
+                }
+            ).collect();
+
+        SceneIndex { entries }
+    }
+
+    pub fn entries(&self) -> &[IndexEntry] {
+        &self.entries
+    }
+
+    pub fn get_position(&self, id: SceneObjectId) -> Option<Vec2> {
+        self.entries.iter().find(|e| e.id == id).and_then(|e| e.position)
+    }
+
+    pub fn find_by_tag(&self, tag: &str) -> Vec<SceneObjectId> {
+        self.entries.iter().filter(|e| e.tags.contains(&tag)).map(|e| e.id).collect()
+    }
+
+    pub fn find_of_type<T: Any>(&self) -> Vec<SceneObjectId> {
+        let type_id = TypeId::of::<T>();
+        self.entries.iter().filter(|e| e.type_id == type_id).map(|e| e.id).collect()
+    }
+
+    pub fn nearest_with_tag(&self, position: Vec2, tag: &str) -> Option<(SceneObjectId, Vec2)> {
+        nearest(
+            self.entries.iter()
+                .filter(|e| e.tags.contains(&tag))
+                .filter_map(|e| e.position.map(|p| (e.id, p))),
+            position
+        )
+    }
+}
+
+pub fn nearest<I: Iterator<Item=(SceneObjectId, Vec2)>>(candidates: I, position: Vec2) -> Option<(SceneObjectId, Vec2)> {
+    let mut result : Option<(f32, SceneObjectId, Vec2)> = None;
+
+    for (id, p) in candidates {
+        let distance_sq = (p - position).len_sq();
+
+        match result {
+            Some((best, _, _)) if best <= distance_sq => { },
+            _ => { result = Some((distance_sq, id, p)); }
+        }
+    }
+
+    result.map(|(_, id, p)| (id, p))
+}
+
+#[cfg(test)]
+struct Critter {
+    tags: &'static [&'static str],
+    transform: Transform,
+    velocity: Vec2,
+}
+
+#[cfg(test)]
+impl Critter {
+    fn at(x: f32, tags: &'static [&'static str]) -> Critter {
+        let mut transform = Transform::new();
+        transform.set_translation(Vec2::from_coords(x, 0.0));
+        Critter { tags, transform, velocity: Vec2::new() }
+    }
+}
+
+#[cfg(test)]
+impl GameObject for Critter {
+    fn update(&mut self, _ctx: &mut Engine, _event_mailbox: &mut dyn EventMailbox, _dt: f32) -> bool { true }
+
+    fn render(&self, _ctx: &mut DrawContext) {}
+
+    fn get_physical_object(&self) -> Option<&dyn PhysicalObject> { Some(self) }
+
+    fn get_physical_object_mut(&mut self) -> Option<&mut dyn PhysicalObject> { Some(self) }
+
+    fn get_tags(&self) -> &[&'static str] { self.tags }
+}
+
+#[cfg(test)]
+impl PhysicalObject for Critter {
+    fn get_transform(&self) -> &Transform { &self.transform }
+
+    fn get_transform_mut(&mut self) -> &mut Transform { &mut self.transform }
+
+    fn get_velocity(&self) -> &Vec2 { &self.velocity }
+
+    fn get_velocity_mut(&mut self) -> &mut Vec2 { &mut self.velocity }
+}
+
+#[cfg(test)]
+struct Prop;
+
+#[cfg(test)]
+impl GameObject for Prop {
+    fn update(&mut self, _ctx: &mut Engine, _event_mailbox: &mut dyn EventMailbox, _dt: f32) -> bool { true }
+
+    fn render(&self, _ctx: &mut DrawContext) {}
+
+    fn get_tags(&self) -> &[&'static str] { &["enemy"] }
+}
+
+#[test]
+fn test_tag_and_type_queries() {
+    let mut scene = Scene::new();
+
+    let far = scene.add_object(Critter::at(100.0, &["enemy"]));
+    let near = scene.add_object(Critter::at(-20.0, &["enemy", "flying"]));
+    let friend = scene.add_object(Critter::at(1.0, &["friend"]));
+    let prop = scene.add_object(Prop);
+
+    let index = scene.build_index();
+
+    assert_eq!(index.find_by_tag("enemy"), vec![far, near, prop]);
+    assert_eq!(index.find_by_tag("flying"), vec![near]);
+    assert!(index.find_by_tag("boss").is_empty());
+
+    assert_eq!(index.find_of_type::<Critter>(), vec![far, near, friend]);
+    assert_eq!(index.find_of_type::<Prop>(), vec![prop]);
+
+    // Objects without a position are never the nearest
+    assert_eq!(index.nearest_with_tag(Vec2::new(), "enemy"), Some((near, Vec2::from_coords(-20.0, 0.0))));
+    assert_eq!(index.nearest_with_tag(Vec2::from_coords(70.0, 0.0), "enemy").map(|(id, _)| id), Some(far));
+    assert_eq!(scene.nearest_with_tag(Vec2::new(), "enemy"), index.nearest_with_tag(Vec2::new(), "enemy"));
+    assert_eq!(index.nearest_with_tag(Vec2::new(), "boss"), None);
+}
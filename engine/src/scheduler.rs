@@ -0,0 +1,356 @@
+use std::collections::VecDeque;
+
+use game_object::{EventType, EventReceiver, EventMailbox};
+use generational::{GenerationalArena, GenerationalIndex};
+use scene::{Scene, SceneObjectId};
+use Engine;
+
+#[derive(Hash, Eq, PartialEq, Clone, Copy, Debug)]
+pub struct TimerId {
+    id: GenerationalIndex,
+}
+
+struct ScheduledEvent {
+    remaining: f32,
+    interval: Option<f32>,
+    owner: Option<SceneObjectId>,
+    event: EventType,
+    receiver: EventReceiver,
+}
+
+// Delivers events after a delay, or over and over on an interval, instead of
+// every object counting down its own f32.
+pub struct Scheduler {
+    timers: GenerationalArena<ScheduledEvent>,
+}
+
+impl Default for Scheduler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Scheduler {
+    pub fn new() -> Scheduler {
+        Scheduler {
+            timers: GenerationalArena::new(),
+        }
+    }
+
+    pub fn schedule(&mut self, delay: f32, event: EventType, receiver: EventReceiver) -> TimerId {
+        self.add(delay, None, None, event, receiver)
+    }
+
+    // Scheduled on behalf of an object, which can cancel it again with
+    // cancel_owned_by without knowing the TimerId
+    pub fn schedule_for(&mut self, owner: SceneObjectId, delay: f32, event: EventType, receiver: EventReceiver) -> TimerId {
+        self.add(delay, None, Some(owner), event, receiver)
+    }
+
+    pub fn schedule_repeating(&mut self, interval: f32, event: EventType, receiver: EventReceiver) -> TimerId {
+        // Zero would fire on every single update
+        let interval = interval.max(0.001);
+        self.add(interval, Some(interval), None, event, receiver)
+    }
+
+    fn add(
+        &mut self,
+        delay: f32,
+        interval: Option<f32>,
+        owner: Option<SceneObjectId>,
+        event: EventType,
+        receiver: EventReceiver
+    ) -> TimerId {
+        let scheduled_event =
+            ScheduledEvent {
+                remaining: delay,
+                interval,
+                owner,
+                event,
+                receiver,
+            };
+
+        TimerId { id: self.timers.insert(scheduled_event) }
+    }
+
+    // Returns false if the timer already fired or was cancelled
+    pub fn cancel(&mut self, id: TimerId) -> bool {
+        self.timers.remove(id.id).is_some()
+    }
+
+    // Returns how many timers were cancelled
+    pub fn cancel_owned_by(&mut self, owner: SceneObjectId) -> usize {
+        let owned: Vec<_> =
+            self.timers.iter()
+                .filter(|(_, timer)| timer.owner == Some(owner))
+                .map(|(id, _)| id)
+                .collect();
+
+        for id in owned.iter() {
+            self.timers.remove(*id);
+        }
+
+        owned.len()
+    }
+
+    pub fn is_pending(&self, id: TimerId) -> bool {
+        self.timers.contains(id.id)
+    }
+
+    pub fn get_remaining(&self, id: TimerId) -> Option<f32> {
+        self.timers.get(id.id).map(|t| t.remaining.max(0.0))
+    }
+
+    pub fn update(&mut self, dt: f32, event_mailbox: &mut dyn EventMailbox) {
+        let mut finished = Vec::new();
+
+        for (id, timer) in self.timers.iter_mut() {
+            timer.remaining -= dt;
+
+            if timer.remaining > 0.0 {
+                continue;
+            }
+
+            event_mailbox.submit_event(timer.event.clone(), timer.receiver.clone());
+
+            match timer.interval {
+                // Fires at most once per update. Intervals missed during a
+                // long frame are dropped, but the timer stays in step.
+                Some(interval) => { timer.remaining = interval - (-timer.remaining % interval); },
+                None => { finished.push(id); }
+            }
+        }
+
+        for id in finished {
+            self.timers.remove(id);
+        }
+    }
+}
+
+#[derive(Hash, Eq, PartialEq, Clone, Copy, Debug)]
+pub struct SequenceId {
+    id: GenerationalIndex,
+}
+
+type Condition = Box<dyn FnMut(&Scene) -> bool>;
+type Action = Box<dyn FnMut(&mut Scene, &mut Engine)>;
+
+enum Step {
+    Wait(f32),
+    WaitUntil(Condition),
+    Run(Action),
+}
+
+// A scripted series of steps, run by the scene one update at a time. Meant
+// for cutscenes and other sequences that would otherwise be a state machine.
+//
+//     let intro =
+//         Sequence::new()
+//             .wait(1.0)
+//             .then(|scene, ctx| { ... })
+//             .wait_until(|scene| scene.find_by_tag("god").is_empty())
+//             .submit_event(EventType::Interact, EventReceiver::Broadcast);
+//
+//     scene.run_sequence(intro);
+pub struct Sequence {
+    steps: VecDeque<Step>,
+}
+
+impl Default for Sequence {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Sequence {
+    pub fn new() -> Sequence {
+        Sequence {
+            steps: VecDeque::new(),
+        }
+    }
+
+    pub fn wait(mut self, duration: f32) -> Sequence {
+        self.steps.push_back(Step::Wait(duration));
+        self
+    }
+
+    pub fn wait_until<F: FnMut(&Scene) -> bool + 'static>(mut self, condition: F) -> Sequence {
+        self.steps.push_back(Step::WaitUntil(Box::new(condition)));
+        self
+    }
+
+    pub fn then<F: FnMut(&mut Scene, &mut Engine) + 'static>(mut self, action: F) -> Sequence {
+        self.steps.push_back(Step::Run(Box::new(action)));
+        self
+    }
+
+    pub fn submit_event(self, event: EventType, receiver: EventReceiver) -> Sequence {
+        self.then(move |scene, _| scene.submit_event(event.clone(), receiver.clone()))
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    // Runs steps until one of them has to wait for a later update
+    pub fn step(&mut self, scene: &mut Scene, engine: &mut Engine, dt: f32) {
+        self.advance(scene, dt, &mut |action, scene| action(scene, engine));
+    }
+
+    fn advance(&mut self, scene: &mut Scene, dt: f32, run: &mut dyn FnMut(&mut Action, &mut Scene)) {
+        let mut dt = dt;
+
+        while let Some(step) = self.steps.front_mut() {
+            let done =
+                match step {
+                    Step::Wait(remaining) => {
+                        let used = remaining.min(dt);
+                        *remaining -= used;
+                        dt -= used;
+                        *remaining <= 0.0
+                    },
+                    Step::WaitUntil(condition) => condition(scene),
+                    Step::Run(action) => {
+                        run(action, scene);
+                        true
+                    }
+                };
+
+            if !done {
+                break;
+            }
+
+            self.steps.pop_front();
+        }
+    }
+}
+
+pub struct SequenceRunner {
+    sequences: GenerationalArena<Option<Sequence>>,
+}
+
+impl Default for SequenceRunner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SequenceRunner {
+    pub fn new() -> SequenceRunner {
+        SequenceRunner {
+            sequences: GenerationalArena::new(),
+        }
+    }
+
+    pub fn start(&mut self, sequence: Sequence) -> SequenceId {
+        SequenceId { id: self.sequences.insert(Some(sequence)) }
+    }
+
+    pub fn cancel(&mut self, id: SequenceId) -> bool {
+        self.sequences.remove(id.id).is_some()
+    }
+
+    pub fn is_running(&self, id: SequenceId) -> bool {
+        self.sequences.contains(id.id)
+    }
+
+    // Sequences are taken out of the runner while they run, since their steps
+    // get the whole scene to work with
+    pub fn take(&mut self, id: SequenceId) -> Option<Sequence> {
+        self.sequences.get_mut(id.id).and_then(|s| s.take())
+    }
+
+    pub fn put_back(&mut self, id: SequenceId, sequence: Sequence) {
+        if sequence.is_finished() {
+            self.sequences.remove(id.id);
+        } else if let Some(slot) = self.sequences.get_mut(id.id) {
+            *slot = Some(sequence);
+        }
+    }
+
+    pub fn ids(&self) -> Vec<SequenceId> {
+        self.sequences.ids().into_iter().map(|id| SequenceId { id }).collect()
+    }
+}
+
+#[cfg(test)]
+struct CollectingMailbox {
+    events: Vec<&'static str>,
+}
+
+#[cfg(test)]
+impl EventMailbox for CollectingMailbox {
+    fn submit_event_with_priority(&mut self, event: EventType, _receiver: EventReceiver, _priority: ::game_object::EventPriority) {
+        self.events.push(event.name());
+    }
+
+    fn submit_command(&mut self, _command: ::game_object::SceneCommand) {}
+}
+
+#[test]
+fn test_scheduler_timers() {
+    let mut scheduler = Scheduler::new();
+    let mut mailbox = CollectingMailbox { events: Vec::new() };
+
+    let delayed = scheduler.schedule(1.0, EventType::Interact, EventReceiver::Broadcast);
+    let repeating = scheduler.schedule_repeating(0.5, EventType::Suck, EventReceiver::Broadcast);
+    let cancelled = scheduler.schedule(0.2, EventType::DeleteMe, EventReceiver::Broadcast);
+
+    assert!(scheduler.cancel(cancelled));
+    assert!(!scheduler.cancel(cancelled));
+
+    scheduler.update(0.75, &mut mailbox);
+    assert_eq!(mailbox.events, vec!["Suck"]);
+    assert_eq!(scheduler.get_remaining(delayed), Some(0.25));
+
+    // Repeating timers do not catch up on missed intervals
+    scheduler.update(0.75, &mut mailbox);
+    assert_eq!(mailbox.events, vec!["Suck", "Interact", "Suck"]);
+    assert!(!scheduler.is_pending(delayed));
+    assert!(scheduler.is_pending(repeating));
+    assert_eq!(scheduler.get_remaining(repeating), Some(0.5));
+
+    // A zero interval is raised to the 0.001 minimum, and a long stall still
+    // only fires it once
+    let mut scheduler = Scheduler::new();
+    let mut mailbox = CollectingMailbox { events: Vec::new() };
+    let fast = scheduler.schedule_repeating(0.0, EventType::Interact, EventReceiver::Broadcast);
+    scheduler.update(10.0, &mut mailbox);
+    assert_eq!(mailbox.events.len(), 1);
+    assert!(scheduler.get_remaining(fast).unwrap() <= 0.001);
+}
+
+#[test]
+fn test_sequence_order() {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    let ready = Rc::new(Cell::new(false));
+    let condition = ready.clone();
+
+    let mut sequence =
+        Sequence::new()
+            .wait(1.0)
+            .then(|_, _| {})
+            .wait_until(move |_| condition.get())
+            .then(|_, _| {})
+            .then(|_, _| {});
+
+    let mut scene = Scene::new();
+    let mut runs = 0;
+
+    sequence.advance(&mut scene, 0.5, &mut |_, _| runs += 1);
+    assert_eq!(runs, 0);
+
+    sequence.advance(&mut scene, 0.6, &mut |_, _| runs += 1);
+    assert_eq!(runs, 1);
+
+    sequence.advance(&mut scene, 1.0, &mut |_, _| runs += 1);
+    assert_eq!(runs, 1);
+    assert!(!sequence.is_finished());
+
+    ready.set(true);
+    sequence.advance(&mut scene, 0.0, &mut |_, _| runs += 1);
+    assert_eq!(runs, 3);
+    assert!(sequence.is_finished());
+}
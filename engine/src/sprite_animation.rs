@@ -0,0 +1,496 @@
+use std::fs::File;
+use std::io::Read;
+
+use serde_json;
+
+use animated_sprite::{Aggregatable, Animatable, SpriteTrait};
+use custom_event::TypedEventMailbox;
+use drawable::{DrawContext, DrawParams, Drawable};
+use extent::Extent;
+use game_object::{EventMailbox, EventReceiver};
+use offset::Offset;
+use texture_registry::Texture;
+use transform::Transform;
+use vector::Vec2;
+use BlendMode;
+use Color;
+use Engine;
+use Error;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum PlayMode {
+    #[default]
+    Loop,
+    // Stops on the last frame
+    Once,
+    // Runs forwards and backwards again without repeating the end frames
+    PingPong,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct FrameDefinition {
+    // Column and row in the sprite sheet
+    pub cell: (i32, i32),
+    // Seconds, the clip's frame_duration if not set
+    #[serde(default)]
+    pub duration: Option<f32>,
+    // Added to the origin of the clip while this frame shows
+    #[serde(default)]
+    pub offset: Vec2,
+    // Names of the AnimationEvents fired when the frame comes up
+    #[serde(default)]
+    pub events: Vec<String>,
+}
+
+fn default_frame_duration() -> f32 { 0.1 }
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ClipDefinition {
+    pub name: String,
+    pub frames: Vec<FrameDefinition>,
+    #[serde(default = "default_frame_duration")]
+    pub frame_duration: f32,
+    #[serde(default)]
+    pub mode: PlayMode,
+    #[serde(default)]
+    pub origin: Vec2,
+}
+
+// Named clips cut out of one sprite sheet, e.g.
+//
+//     {
+//         "texture": "assets/images/God/god.png",
+//         "frame_size": [240, 480],
+//         "clips": [
+//             { "name": "idle", "frames": [{ "cell": [0, 2] }] },
+//             {
+//                 "name": "walk",
+//                 "mode": "ping_pong",
+//                 "frames": [{ "cell": [1, 2], "events": ["footstep"] }, { "cell": [2, 2], "duration": 0.2 }]
+//             }
+//         ]
+//     }
+//
+// Offsets are in pixels of the sheet and move the drawn frame away from the
+// position of the sprite.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct AnimationDefinition {
+    pub texture: String,
+    pub frame_size: (i32, i32),
+    #[serde(default)]
+    pub origin: Vec2,
+    pub clips: Vec<ClipDefinition>,
+}
+
+impl AnimationDefinition {
+    pub fn load(filename: &str) -> Result<AnimationDefinition, Error> {
+        let mut data = String::new();
+
+        File::open(filename)
+            .and_then(|mut f| f.read_to_string(&mut data))
+            .map_err(|_| Error::IO { path: Some(filename.to_string()) })?;
+
+        serde_json::from_str::<AnimationDefinition>(&data)
+            .map_err(|e| Error::FatalError(format!("Could not parse {}: {}", filename, e)))
+    }
+}
+
+// Fired when a frame with events comes up, including the first frame of a
+// clip when it starts
+#[derive(Clone, Debug)]
+pub struct AnimationEvent {
+    pub name: String,
+    pub clip: String,
+    pub frame: usize,
+}
+
+#[derive(Clone)]
+struct Frame {
+    texture: Texture,
+    duration: f32,
+    offset: Vec2,
+    events: Vec<String>,
+}
+
+#[derive(Clone)]
+struct Clip {
+    name: String,
+    frames: Vec<Frame>,
+    mode: PlayMode,
+}
+
+// Where a clip is at, kept apart from the textures
+#[derive(Clone, Debug, PartialEq)]
+struct Playback {
+    frame: usize,
+    time: f32,
+    direction: i32,
+    finished: bool,
+}
+
+impl Playback {
+    fn new() -> Playback {
+        Playback {
+            frame: 0,
+            time: 0.0,
+            direction: 1,
+            finished: false,
+        }
+    }
+
+    // Returns the frames entered on the way, in order
+    fn step(&mut self, durations: &[f32], mode: PlayMode, dt: f32) -> Vec<usize> {
+        let mut entered = Vec::new();
+
+        if self.finished || durations.is_empty() {
+            return entered;
+        }
+
+        self.time += dt;
+
+        loop {
+            let duration = durations[self.frame];
+
+            // Frames without a duration hold the clip
+            if duration <= 0.0 || self.time < duration {
+                break;
+            }
+
+            self.time -= duration;
+
+            match self.next_frame(durations.len(), mode) {
+                Some(frame) => {
+                    self.frame = frame;
+                    entered.push(frame);
+                },
+                None => {
+                    self.finished = true;
+                    self.time = 0.0;
+                    break;
+                }
+            }
+        }
+
+        entered
+    }
+
+    fn next_frame(&mut self, frame_count: usize, mode: PlayMode) -> Option<usize> {
+        let frame = self.frame as i32;
+        let last = frame_count as i32 - 1;
+
+        match mode {
+            PlayMode::Loop => Some(((frame + 1) % frame_count as i32) as usize),
+            PlayMode::Once => if frame < last { Some(self.frame + 1) } else { None },
+            PlayMode::PingPong => {
+                if last == 0 {
+                    return Some(0);
+                }
+
+                if frame + self.direction < 0 || frame + self.direction > last {
+                    self.direction = -self.direction;
+                }
+
+                Some((frame + self.direction) as usize)
+            }
+        }
+    }
+}
+
+// A sprite playing clips from an AnimationDefinition, selected by name
+#[derive(Clone)]
+pub struct SpriteAnimation {
+    clips: Vec<Clip>,
+    frame_extent: Extent,
+    origin: Vec2,
+    current_clip: usize,
+    playback: Playback,
+    events: Vec<AnimationEvent>,
+    // Events that were already pending before the last step
+    stale_events: usize,
+    transform: Transform,
+    draw_params: DrawParams,
+}
+
+impl SpriteAnimation {
+    pub fn new(definition: &AnimationDefinition, texture: Texture) -> Result<SpriteAnimation, Error> {
+        let (width, height) = definition.frame_size;
+        if width <= 0 || height <= 0 || definition.clips.is_empty() {
+            return Err(Error::InvalidTileSize);
+        }
+
+        let frame_extent = Extent::new(width, height);
+        let mut clips = Vec::new();
+
+        for clip in definition.clips.iter() {
+            let mut frames = Vec::new();
+
+            for frame in clip.frames.iter() {
+                let (column, row) = frame.cell;
+                let offset = Offset::from_coords(column * width, row * height);
+
+                frames.push(Frame {
+                    texture: texture.sub_texture(offset, frame_extent)?,
+                    duration: frame.duration.unwrap_or(clip.frame_duration),
+                    offset: clip.origin + frame.offset,
+                    events: frame.events.clone(),
+                });
+            }
+
+            clips.push(Clip {
+                name: clip.name.clone(),
+                frames,
+                mode: clip.mode,
+            });
+        }
+
+        let mut animation =
+            SpriteAnimation {
+                clips,
+                frame_extent,
+                origin: definition.origin,
+                current_clip: 0,
+                playback: Playback::new(),
+                events: Vec::new(),
+                stale_events: 0,
+                transform: Transform::new(),
+                draw_params: DrawParams::new(),
+            };
+        animation.start_clip(0);
+
+        Ok(animation)
+    }
+
+    // Loads the definition and the texture it names
+    pub fn load(ctx: &mut Engine, filename: &str) -> Result<SpriteAnimation, Error> {
+        let definition = AnimationDefinition::load(filename)?;
+        let texture = ctx.get_texture_registry().load(&definition.texture)?;
+
+        SpriteAnimation::new(&definition, texture)
+    }
+
+    // Keeps playing if the clip is already on, see restart
+    pub fn play(&mut self, name: &str) -> Result<(), Error> {
+        let index =
+            self.clips.iter()
+                .position(|clip| clip.name == name)
+                .ok_or_else(|| Error::FatalError(format!("Unknown animation clip {}", name)))?;
+
+        if index != self.current_clip {
+            self.start_clip(index);
+        }
+
+        Ok(())
+    }
+
+    pub fn restart(&mut self) {
+        let clip = self.current_clip;
+        self.start_clip(clip);
+    }
+
+    // Events fired since the last call, in the order they were fired. Events
+    // that are not taken are dropped by the step after the one that fired
+    // them, so take or submit them every update.
+    pub fn take_events(&mut self) -> Vec<AnimationEvent> {
+        self.stale_events = 0;
+        std::mem::take(&mut self.events)
+    }
+
+    // Sends every pending event as a typed event, see TypedEventMailbox
+    pub fn submit_events(&mut self, event_mailbox: &mut dyn EventMailbox, receiver: EventReceiver) {
+        for event in self.take_events() {
+            event_mailbox.submit_typed(event, receiver.clone());
+        }
+    }
+
+    pub fn has_clip(&self, name: &str) -> bool {
+        self.clips.iter().any(|clip| clip.name == name)
+    }
+
+    pub fn get_clip_names(&self) -> Vec<&str> {
+        self.clips.iter().map(|clip| clip.name.as_str()).collect()
+    }
+
+    pub fn get_clip_name(&self) -> &str {
+        &self.clips[self.current_clip].name
+    }
+
+    // Index into the frames of the current clip
+    pub fn get_frame(&self) -> usize {
+        self.playback.frame
+    }
+
+    // Only clips played once finish
+    pub fn is_finished(&self) -> bool {
+        self.playback.finished
+    }
+
+    pub fn set_color_mod(&mut self, color: Color) {
+        self.draw_params.color = color;
+    }
+
+    pub fn set_alpha(&mut self, alpha: u8) {
+        self.draw_params.alpha = alpha;
+    }
+
+    pub fn set_flip(&mut self, horizontal: bool, vertical: bool) {
+        self.draw_params.flip_horizontal = horizontal;
+        self.draw_params.flip_vertical = vertical;
+    }
+
+    pub fn set_blend_mode(&mut self, blend_mode: BlendMode) {
+        self.draw_params.blend_mode = Some(blend_mode);
+    }
+
+    fn start_clip(&mut self, index: usize) {
+        self.current_clip = index;
+        self.playback = Playback::new();
+        self.fire_events(0);
+    }
+
+    fn fire_events(&mut self, frame: usize) {
+        let clip = &self.clips[self.current_clip];
+
+        if let Some(frame_data) = clip.frames.get(frame) {
+            for name in frame_data.events.iter() {
+                self.events.push(AnimationEvent {
+                    name: name.clone(),
+                    clip: clip.name.clone(),
+                    frame,
+                });
+            }
+        }
+    }
+
+    fn current_frame(&self) -> Option<&Frame> {
+        self.clips[self.current_clip].frames.get(self.playback.frame)
+    }
+}
+
+impl Animatable for SpriteAnimation {
+    // Modes are the clips in the order they are defined. Modes out of range
+    // are ignored, play is the better way to pick clips.
+    fn set_mode(&mut self, mode: i32) {
+        if mode >= 0 && (mode as usize) < self.clips.len() && mode as usize != self.current_clip {
+            self.start_clip(mode as usize);
+        }
+    }
+
+    fn get_mode_count(&self) -> i32 {
+        self.clips.len() as i32
+    }
+
+    fn step_time(&mut self, dt: f32) {
+        self.events.drain(..self.stale_events);
+
+        let clip = &self.clips[self.current_clip];
+        let durations : Vec<f32> = clip.frames.iter().map(|frame| frame.duration).collect();
+
+        for frame in self.playback.step(&durations, clip.mode, dt) {
+            self.fire_events(frame);
+        }
+
+        self.stale_events = self.events.len();
+    }
+}
+
+impl SpriteTrait for SpriteAnimation {
+    fn set_position(&mut self, position: Vec2) {
+        self.transform.set_translation(position);
+    }
+
+    fn get_position(&self) -> Vec2 {
+        self.transform.get_translation()
+    }
+
+    fn set_transform(&mut self, transform: &Transform) {
+        self.transform = transform.clone();
+    }
+
+    fn set_scale(&mut self, scale: f32) {
+        self.transform.set_scale(scale);
+    }
+
+    fn calculate_size(&self) -> Vec2 {
+        self.frame_extent.to_vec() * self.transform.get_scale()
+    }
+
+    fn get_draw_params(&self) -> DrawParams {
+        self.draw_params
+    }
+
+    fn set_draw_params(&mut self, draw_params: DrawParams) {
+        self.draw_params = draw_params;
+    }
+}
+
+impl Drawable for SpriteAnimation {
+    fn draw(&self, ctx: &mut DrawContext) {
+        if let Some(frame) = self.current_frame() {
+            let mut transform = self.transform.clone();
+            transform.translate((self.origin + frame.offset) * self.transform.get_scale());
+
+            ctx.draw_with(&frame.texture, &transform, &self.draw_params);
+        }
+    }
+}
+
+impl Aggregatable for SpriteAnimation { }
+
+#[test]
+fn test_playback_modes() {
+    let durations = [1.0, 1.0, 1.0];
+
+    let mut looping = Playback::new();
+    assert_eq!(looping.step(&durations, PlayMode::Loop, 3.5), vec![1, 2, 0]);
+    assert!(!looping.finished);
+
+    let mut once = Playback::new();
+    assert_eq!(once.step(&durations, PlayMode::Once, 10.0), vec![1, 2]);
+    assert!(once.finished);
+    assert_eq!(once.frame, 2);
+
+    let mut ping_pong = Playback::new();
+    assert_eq!(ping_pong.step(&durations, PlayMode::PingPong, 4.0), vec![1, 2, 1, 0]);
+}
+
+#[test]
+fn test_events_in_order() {
+    let definition : AnimationDefinition =
+        serde_json::from_str(r#"{
+            "texture": "sheet.png",
+            "frame_size": [10, 10],
+            "clips": [
+                { "name": "idle", "frames": [{ "cell": [0, 0] }] },
+                {
+                    "name": "swing",
+                    "mode": "once",
+                    "frame_duration": 0.5,
+                    "frames": [
+                        { "cell": [0, 0], "events": ["wind_up"] },
+                        { "cell": [1, 0] },
+                        { "cell": [2, 0], "events": ["hit", "whoosh"] }
+                    ]
+                }
+            ]
+        }"#).unwrap();
+
+    let mut animation = SpriteAnimation::new(&definition, Texture::detached(30, 10)).unwrap();
+    let names = |events: Vec<AnimationEvent>| events.into_iter().map(|e| (e.name, e.frame)).collect::<Vec<_>>();
+
+    animation.play("swing").unwrap();
+    animation.step_time(1.5);
+
+    assert_eq!(
+        names(animation.take_events()),
+        vec![("wind_up".to_string(), 0), ("hit".to_string(), 2), ("whoosh".to_string(), 2)]
+    );
+
+    // Events nobody took are gone after the next step
+    animation.play("idle").unwrap();
+    animation.play("swing").unwrap();
+    animation.step_time(0.6);
+    animation.step_time(0.6);
+
+    assert_eq!(names(animation.take_events()), vec![("hit".to_string(), 2), ("whoosh".to_string(), 2)]);
+}
@@ -1,10 +1,12 @@
-use drawable::{DrawContext, Drawable};
+use drawable::{DrawContext, DrawParams, Drawable};
 use texture_registry::Texture;
 
 use super::Error;
 use rect::Rect2D;
 use transform::Transform;
 use vector::Vec2;
+use BlendMode;
+use Color;
 
 #[derive(Clone)]
 pub struct StaticSprite {
@@ -12,7 +14,8 @@ pub struct StaticSprite {
     x_size: i32,
     y_size: i32,
     position: Vec2,
-    scale: f32
+    scale: f32,
+    draw_params: DrawParams
 }
 
 impl StaticSprite {
@@ -23,7 +26,8 @@ impl StaticSprite {
                 x_size,
                 y_size,
                 position: Vec2::new(),
-                scale: 1.0
+                scale: 1.0,
+                draw_params: DrawParams::new()
             };
 
         Ok(static_sprite)
@@ -41,6 +45,31 @@ impl StaticSprite {
         self.scale = scale;
     }
 
+    pub fn get_draw_params(&self) -> DrawParams {
+        self.draw_params
+    }
+
+    pub fn set_draw_params(&mut self, draw_params: DrawParams) {
+        self.draw_params = draw_params;
+    }
+
+    pub fn set_color_mod(&mut self, color: Color) {
+        self.draw_params.color = color;
+    }
+
+    pub fn set_alpha(&mut self, alpha: u8) {
+        self.draw_params.alpha = alpha;
+    }
+
+    pub fn set_flip(&mut self, horizontal: bool, vertical: bool) {
+        self.draw_params.flip_horizontal = horizontal;
+        self.draw_params.flip_vertical = vertical;
+    }
+
+    pub fn set_blend_mode(&mut self, blend_mode: BlendMode) {
+        self.draw_params.blend_mode = Some(blend_mode);
+    }
+
     pub fn calculate_size(&mut self) -> Vec2 {
         Vec2 {
             x: self.x_size as f32 * self.scale,
@@ -73,6 +102,6 @@ impl Drawable for StaticSprite {
         transform.set_translation(self.position);
         transform.set_scale(self.scale);
 
-        ctx.draw(&self.texture, &transform);
+        ctx.draw_with(&self.texture, &transform, &self.draw_params);
     }
 }
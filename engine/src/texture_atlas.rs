@@ -0,0 +1,424 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::PathBuf;
+
+use serde_json;
+
+use image::{Image, RGBA};
+use extent::Extent;
+use offset::Offset;
+use texture_registry::{Texture, TextureRegistry};
+use BlendMode;
+use Error;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct AtlasEntry {
+    pub page: usize,
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+// Written next to the page images, with page filenames relative to it
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct AtlasManifest {
+    pub pages: Vec<String>,
+    pub entries: BTreeMap<String, AtlasEntry>,
+}
+
+// Packs images into as few pages as it can. Images larger than a page get a
+// page of their own.
+//
+//     let mut builder = AtlasBuilder::new();
+//     builder.add_file("assets/images/wall.png")?;
+//     let atlas = builder.pack().upload(ctx.get_texture_registry())?;
+//     let wall = atlas.get("assets/images/wall.png");
+pub struct AtlasBuilder {
+    page_size: i32,
+    padding: i32,
+    images: Vec<(String, Image<RGBA>)>,
+}
+
+impl Default for AtlasBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AtlasBuilder {
+    pub fn new() -> AtlasBuilder {
+        AtlasBuilder {
+            page_size: 2048,
+            padding: 1,
+            images: Vec::new(),
+        }
+    }
+
+    pub fn with_page_size(mut self, page_size: i32) -> AtlasBuilder {
+        self.page_size = page_size;
+        self
+    }
+
+    // Transparent pixels between images, so scaled sprites do not pick up
+    // the edges of their neighbours
+    pub fn with_padding(mut self, padding: i32) -> AtlasBuilder {
+        self.padding = padding;
+        self
+    }
+
+    // Adding the same name twice keeps the first image
+    pub fn add_image(&mut self, name: &str, image: Image<RGBA>) {
+        if !self.images.iter().any(|(n, _)| n == name) {
+            self.images.push((name.to_string(), image));
+        }
+    }
+
+    // The image is named by its path
+    pub fn add_file(&mut self, filename: &str) -> Result<(), Error> {
+        if !self.images.iter().any(|(n, _)| n == filename) {
+            let image = Image::load(filename)?;
+            self.add_image(filename, image);
+        }
+
+        Ok(())
+    }
+
+    pub fn pack(&self) -> PackedAtlas {
+        let mut order : Vec<usize> = (0..self.images.len()).collect();
+        order.sort_by_key(|i| {
+            let image = &self.images[*i].1;
+            (-image.height(), -image.width())
+        });
+
+        let mut pages : Vec<Skyline> = Vec::new();
+        let mut entries = BTreeMap::new();
+
+        for i in order {
+            let (ref name, ref image) = self.images[i];
+            let width = image.width() + self.padding;
+            let height = image.height() + self.padding;
+
+            let oversized = width > self.page_size || height > self.page_size;
+
+            let mut placement = None;
+            if !oversized {
+                for (index, skyline) in pages.iter_mut().enumerate() {
+                    if let Some((x, y)) = skyline.insert(width, height) {
+                        placement = Some((index, x, y));
+                        break;
+                    }
+                }
+            }
+
+            let (page, x, y) =
+                match placement {
+                    Some(placement) => placement,
+                    None if oversized => {
+                        // Without padding, so the page is no larger than a
+                        // texture of its own would have been
+                        let mut skyline = Skyline::new(image.width(), image.height());
+                        skyline.insert(image.width(), image.height());
+
+                        pages.push(skyline);
+                        (pages.len() - 1, 0, 0)
+                    },
+                    None => {
+                        let mut skyline = Skyline::new(self.page_size, self.page_size);
+                        let (x, y) = skyline.insert(width, height).unwrap();
+
+                        pages.push(skyline);
+                        (pages.len() - 1, x, y)
+                    }
+                };
+
+            let entry = AtlasEntry { page, x, y, width: image.width(), height: image.height() };
+            entries.insert(name.clone(), entry);
+        }
+
+        // Pages are cut down to the area in use
+        let transparent = RGBA { r: 0, g: 0, b: 0, a: 0 };
+        let mut page_images : Vec<Image<RGBA>> =
+            pages.iter()
+                .enumerate()
+                .map(|(index, _)| {
+                    let (width, height) =
+                        entries.values()
+                            .filter(|e| e.page == index)
+                            .fold((1, 1), |(w, h), e| (w.max(e.x + e.width), h.max(e.y + e.height)));
+
+                    Image::new(width, height, transparent)
+                })
+                .collect();
+
+        for (name, image) in self.images.iter() {
+            if let Some(entry) = entries.get(name) {
+                page_images[entry.page].blit(image, entry.x, entry.y);
+            }
+        }
+
+        PackedAtlas {
+            pages: page_images,
+            entries,
+        }
+    }
+}
+
+pub struct PackedAtlas {
+    pages: Vec<Image<RGBA>>,
+    entries: BTreeMap<String, AtlasEntry>,
+}
+
+impl PackedAtlas {
+    pub fn get_pages(&self) -> &[Image<RGBA>] {
+        &self.pages
+    }
+
+    pub fn get_entry(&self, name: &str) -> Option<&AtlasEntry> {
+        self.entries.get(name)
+    }
+
+    // Writes `<name>-<page>.png` for every page and `<name>.json` as the
+    // manifest, all into `folder`
+    pub fn save(&self, folder: &str, name: &str) -> Result<(), Error> {
+        std::fs::create_dir_all(folder).map_err(|_| Error::IO { path: Some(folder.to_string()) })?;
+
+        let mut page_names = Vec::new();
+
+        for (index, page) in self.pages.iter().enumerate() {
+            let page_name = format!("{}-{}.png", name, index);
+
+            let mut filename = PathBuf::from(folder);
+            filename.push(&page_name);
+            page.save_png(filename.to_str().unwrap())?;
+
+            page_names.push(page_name);
+        }
+
+        let manifest =
+            AtlasManifest {
+                pages: page_names,
+                entries: self.entries.clone(),
+            };
+
+        let mut filename = PathBuf::from(folder);
+        filename.push(format!("{}.json", name));
+        let filename = filename.to_str().unwrap().to_string();
+
+        let data =
+            serde_json::to_string_pretty(&manifest)
+                .map_err(|e| Error::FatalError(format!("Could not write {}: {}", filename, e)))?;
+
+        File::create(&filename)
+            .and_then(|mut f| f.write_all(data.as_bytes()))
+            .map_err(|_| Error::IO { path: Some(filename.clone()) })
+    }
+
+    pub fn upload(&self, texture_registry: &mut TextureRegistry) -> Result<TextureAtlas, Error> {
+        let mut pages = Vec::new();
+
+        for page in self.pages.iter() {
+            let texture =
+                texture_registry.create_from_rgba(
+                    page.width() as u32,
+                    page.height() as u32,
+                    &page.to_bytes(),
+                    BlendMode::Blend
+                )?;
+
+            pages.push(texture);
+        }
+
+        TextureAtlas::from_pages(&pages, &self.entries)
+    }
+}
+
+// Texture handles cut out of the atlas pages, looked up by name
+pub struct TextureAtlas {
+    textures: HashMap<String, Texture>,
+}
+
+impl TextureAtlas {
+    // Loads pages and manifest written by PackedAtlas::save
+    pub fn load(texture_registry: &mut TextureRegistry, manifest_filename: &str) -> Result<TextureAtlas, Error> {
+        let mut data = String::new();
+
+        File::open(manifest_filename)
+            .and_then(|mut f| f.read_to_string(&mut data))
+            .map_err(|_| Error::IO { path: Some(manifest_filename.to_string()) })?;
+
+        let manifest =
+            serde_json::from_str::<AtlasManifest>(&data)
+                .map_err(|e| Error::FatalError(format!("Could not parse {}: {}", manifest_filename, e)))?;
+
+        let mut folder = PathBuf::from(manifest_filename);
+        folder.pop();
+
+        let mut pages = Vec::new();
+
+        for page in manifest.pages.iter() {
+            let mut filename = folder.clone();
+            filename.push(page);
+            pages.push(texture_registry.load(filename.to_str().unwrap())?);
+        }
+
+        TextureAtlas::from_pages(&pages, &manifest.entries)
+    }
+
+    // Packs the files into an atlas right away
+    pub fn from_files(texture_registry: &mut TextureRegistry, filenames: &[String]) -> Result<TextureAtlas, Error> {
+        let mut builder = AtlasBuilder::new();
+
+        for filename in filenames.iter() {
+            builder.add_file(filename)?;
+        }
+
+        builder.pack().upload(texture_registry)
+    }
+
+    fn from_pages(pages: &[Texture], entries: &BTreeMap<String, AtlasEntry>) -> Result<TextureAtlas, Error> {
+        let mut textures = HashMap::new();
+
+        for (name, entry) in entries.iter() {
+            let page =
+                pages.get(entry.page)
+                    .ok_or_else(|| Error::FatalError(format!("{} is on a missing atlas page", name)))?;
+
+            let texture =
+                page.sub_texture(
+                    Offset::from_coords(entry.x, entry.y),
+                    Extent::new(entry.width, entry.height)
+                )?;
+
+            textures.insert(name.clone(), texture);
+        }
+
+        Ok(TextureAtlas { textures })
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Texture> {
+        self.textures.get(name)
+    }
+
+    pub fn get_names(&self) -> Vec<&str> {
+        self.textures.keys().map(|name| name.as_str()).collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.textures.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.textures.is_empty()
+    }
+}
+
+// Bottom-left skyline packing. The skyline is the top edge of everything
+// placed so far, stored as segments of x, y and width from left to right.
+struct Skyline {
+    width: i32,
+    height: i32,
+    segments: Vec<(i32, i32, i32)>,
+}
+
+impl Skyline {
+    fn new(width: i32, height: i32) -> Skyline {
+        Skyline {
+            width,
+            height,
+            segments: vec![(0, 0, width)],
+        }
+    }
+
+    // Top of a rectangle placed at the start of the segment, if it fits
+    fn fit(&self, index: usize, width: i32, height: i32) -> Option<i32> {
+        let x = self.segments[index].0;
+        if x + width > self.width {
+            return None;
+        }
+
+        let mut y = 0;
+        let mut remaining = width;
+
+        for &(_, segment_y, segment_width) in self.segments[index..].iter() {
+            if remaining <= 0 {
+                break;
+            }
+
+            y = y.max(segment_y);
+            if y + height > self.height {
+                return None;
+            }
+
+            remaining -= segment_width;
+        }
+
+        Some(y)
+    }
+
+    fn insert(&mut self, width: i32, height: i32) -> Option<(i32, i32)> {
+        let mut best : Option<(usize, i32, i32)> = None;
+
+        for index in 0..self.segments.len() {
+            if let Some(y) = self.fit(index, width, height) {
+                if best.map(|(_, _, best_y)| y < best_y).unwrap_or(true) {
+                    best = Some((index, self.segments[index].0, y));
+                }
+            }
+        }
+
+        let (index, x, y) = best?;
+        self.segments.insert(index, (x, y + height, width));
+
+        // Cut away what the new segment covers
+        let right = x + width;
+        while index + 1 < self.segments.len() {
+            let (next_x, next_y, next_width) = self.segments[index + 1];
+            if next_x >= right {
+                break;
+            }
+
+            if next_x + next_width <= right {
+                self.segments.remove(index + 1);
+            } else {
+                self.segments[index + 1] = (right, next_y, next_x + next_width - right);
+                break;
+            }
+        }
+
+        let mut i = 0;
+        while i + 1 < self.segments.len() {
+            if self.segments[i].1 == self.segments[i + 1].1 {
+                self.segments[i].2 += self.segments[i + 1].2;
+                self.segments.remove(i + 1);
+            } else {
+                i += 1;
+            }
+        }
+
+        Some((x, y))
+    }
+}
+
+#[test]
+fn test_skyline_packs_without_overlap() {
+    let mut skyline = Skyline::new(64, 64);
+    let sizes = [(30, 20), (30, 30), (10, 40), (20, 10), (34, 20), (64, 4)];
+
+    let mut placed : Vec<(i32, i32, i32, i32)> = Vec::new();
+
+    for &(width, height) in sizes.iter() {
+        let (x, y) = skyline.insert(width, height).unwrap();
+        assert!(x >= 0 && y >= 0 && x + width <= 64 && y + height <= 64);
+
+        for &(px, py, pw, ph) in placed.iter() {
+            let overlaps = x < px + pw && px < x + width && y < py + ph && py < y + height;
+            assert!(!overlaps);
+        }
+
+        placed.push((x, y, width, height));
+    }
+
+    assert_eq!(skyline.insert(65, 1), None);
+}
@@ -2,11 +2,14 @@ use std::path::Path;
 use stb_image::image::LoadResult;
 use stb_image::image;
 
-use std::collections::BTreeMap;
+use std::cell::{Ref, RefCell, RefMut};
+use std::collections::{BTreeMap, HashMap};
+use std::rc::Rc;
 
 use super::Error;
 
 use extent::Extent;
+use image::{Image, RGBA};
 use offset::Offset;
 
 mod sdl {
@@ -16,13 +19,18 @@ pub use sdl2::render::BlendMode;
 pub use sdl2::video::WindowContext;
 pub use sdl2::surface::Surface;
 pub use sdl2::pixels::PixelFormatEnum;
+pub use sdl2::rect::Rect;
 }
 
+// A handle to part of a texture in the registry. The registry keeps the
+// texture as long as any handle to it is alive, see
+// TextureRegistry::purge_unused.
 #[derive(Clone)]
 pub struct Texture {
     index: usize,
     offset: Offset,
     extent: Extent,
+    handle: Rc<()>,
 }
 
 impl Texture {
@@ -45,27 +53,158 @@ impl Texture {
             Texture {
                 index: self.index,
                 offset: self.offset + offset,
-                extent
+                extent,
+                handle: self.handle.clone(),
             };
 
         Ok(texture)
     }
 }
 
-pub struct TextureData<'t> {
+#[cfg(test)]
+impl Texture {
+    // Not backed by the registry, for tests that never draw
+    pub(crate) fn detached(width: i32, height: i32) -> Texture {
+        Texture {
+            index: 0,
+            offset: Offset::from_coords(0, 0),
+            extent: Extent::new(width, height),
+            handle: Rc::new(()),
+        }
+    }
+}
+
+pub struct TextureData<T> {
     //surface: sdl::Surface<'static>,
-    texture: sdl::Texture<'t>
+    // Colour and alpha modulation is set right before each copy, while
+    // drawing only has shared access to the registry
+    texture: RefCell<T>,
+    extent: Extent,
+    path: Option<(String, sdl::BlendMode)>,
+    // Shared with every handle, so a count of one means only the registry
+    // is left
+    handle: Rc<()>,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TextureStats {
+    pub texture_count: usize,
+    pub cached_paths: usize,
+    // Estimated from the size of the textures at four bytes per pixel
+    pub bytes: usize,
+    pub cache_hits: usize,
+    pub cache_misses: usize,
+}
+
+// Which textures exist, which paths they were loaded from and who still
+// holds handles to them. Kept apart from SDL, the registry stores
+// sdl::Texture in it.
+struct TextureSlots<T> {
+    textures: BTreeMap<usize, TextureData<T>>,
+    paths: HashMap<(String, sdl::BlendMode), usize>,
+    next_index: usize,
+}
+
+impl<T> TextureSlots<T> {
+    fn new() -> TextureSlots<T> {
+        TextureSlots {
+            textures: BTreeMap::new(),
+            paths: HashMap::new(),
+            next_index: 0,
+        }
+    }
+
+    fn insert(&mut self, texture: T, extent: Extent) -> Texture {
+        let texture_data =
+            TextureData {
+    //            surface: surface,
+                texture: RefCell::new(texture),
+                extent,
+                path: None,
+                handle: Rc::new(()),
+            };
+
+        // Indices are never reused, so handles to freed textures can not
+        // point at new ones
+        let index = self.next_index;
+        self.next_index += 1;
+        self.textures.insert(index, texture_data);
+
+        self.handle(index)
+    }
+
+    fn handle(&self, index: usize) -> Texture {
+        let data = self.textures.get(&index).unwrap();
+
+        Texture {
+            index,
+            extent: data.extent,
+            offset: Offset::new(),
+            handle: data.handle.clone(),
+        }
+    }
+
+    fn set_path(&mut self, texture: &Texture, key: (String, sdl::BlendMode)) {
+        if let Some(data) = self.textures.get_mut(&texture.index) {
+            data.path = Some(key.clone());
+        }
+        self.paths.insert(key, texture.index);
+    }
+
+    fn unload(&mut self, path: &str) -> bool {
+        let indices : Vec<usize> =
+            self.paths.iter()
+                .filter(|((p, _), _)| p == path)
+                .map(|(_, index)| *index)
+                .collect();
+
+        self.paths.retain(|(p, _), _| p != path);
+
+        for index in indices.iter() {
+            if let Some(data) = self.textures.get_mut(index) {
+                data.path = None;
+
+                if Rc::strong_count(&data.handle) == 1 {
+                    self.textures.remove(index);
+                }
+            }
+        }
+
+        !indices.is_empty()
+    }
+
+    fn purge_unused(&mut self) -> usize {
+        let unused : Vec<usize> =
+            self.textures.iter()
+                .filter(|(_, data)| Rc::strong_count(&data.handle) == 1)
+                .map(|(index, _)| *index)
+                .collect();
+
+        for index in unused.iter() {
+            if let Some(data) = self.textures.remove(index) {
+                if let Some(key) = data.path {
+                    self.paths.remove(&key);
+                }
+            }
+        }
+
+        unused.len()
+    }
 }
 
 pub struct TextureRegistry<'t> {
-    textures: BTreeMap<usize, TextureData<'t>>,
+    slots: TextureSlots<sdl::Texture<'t>>,
+    cache_hits: usize,
+    cache_misses: usize,
     texture_creator: &'t sdl::TextureCreator<sdl::WindowContext>
 }
 
 impl<'t> TextureRegistry<'t> {
     pub fn new(texture_creator: &'t sdl::TextureCreator<sdl::WindowContext>) -> TextureRegistry<'t> {
         TextureRegistry {
-            textures: BTreeMap::new(),
+            slots: TextureSlots::new(),
+            cache_hits: 0,
+            cache_misses: 0,
             texture_creator
         }
     }
@@ -73,54 +212,304 @@ impl<'t> TextureRegistry<'t> {
         self.load2(path, sdl::BlendMode::Blend)
     }
 
+    // Loading the same path again hands out the texture that is already
+    // there, as long as it has not been unloaded
     pub fn load2(&mut self, path: &str, blend_mode: sdl::BlendMode) -> Result<Texture, Error> {
-        let png_img = match image::load(Path::new(path)) {
-            LoadResult::ImageU8(bytes) => { bytes },
-            LoadResult::ImageF32(_) => panic!("Is float"),
-            _ => return Err(Error::IO { path: Some(path.to_string()) })
-        };
+        let key = (path.to_string(), blend_mode);
+
+        if let Some(index) = self.slots.paths.get(&key) {
+            self.cache_hits += 1;
+            return Ok(self.slots.handle(*index));
+        }
+
+        self.cache_misses += 1;
+
+        let (width, height, data) = load_rgba(path)?;
+        let texture = self.create_from_rgba(width, height, &data, blend_mode)?;
+
+        self.slots.set_path(&texture, key);
+
+        Ok(texture)
+    }
+
+    pub fn is_loaded(&self, path: &str) -> bool {
+        self.slots.paths.keys().any(|(p, _)| p == path)
+    }
+
+    pub fn get_loaded_paths(&self) -> Vec<String> {
+        let mut paths : Vec<String> = self.slots.paths.keys().map(|(p, _)| p.clone()).collect();
+        paths.sort();
+        paths.dedup();
+        paths
+    }
+
+    // Reads the file again and swaps the pixels in, so every handle to the
+    // texture shows the new image. Handles keep the size they were made
+    // with, so images that change size are best reloaded at the same size.
+    // Returns false if the path was never loaded.
+    pub fn reload(&mut self, path: &str) -> Result<bool, Error> {
+        let keys : Vec<((String, sdl::BlendMode), usize)> =
+            self.slots.paths.iter()
+                .filter(|((p, _), _)| p == path)
+                .map(|(key, index)| (key.clone(), *index))
+                .collect();
+
+        if keys.is_empty() {
+            return Ok(false);
+        }
+
+        let (width, height, data) = load_rgba(path)?;
+
+        for ((_, blend_mode), index) in keys {
+            let texture = self.create_sdl_texture(width, height, &data, blend_mode)?;
+
+            if let Some(texture_data) = self.slots.textures.get_mut(&index) {
+                let extent = Extent::new(width as i32, height as i32);
+                if extent.width != texture_data.extent.width || extent.height != texture_data.extent.height {
+                    println!("{} changed size, existing sprites keep the old one", path);
+                }
+
+                texture_data.texture = RefCell::new(texture);
+                texture_data.extent = extent;
+            }
+        }
+
+        Ok(true)
+    }
+
+    // Forgets the path, so the next load reads the file again. The texture
+    // itself is freed right away if nothing uses it, otherwise by
+    // purge_unused once the last handle is gone.
+    pub fn unload(&mut self, path: &str) -> bool {
+        self.slots.unload(path)
+    }
+
+    // Frees every texture without handles, loaded or created. Cached paths
+    // go too, so the next load of one reads the file again. Returns the
+    // number of textures freed.
+    pub fn purge_unused(&mut self) -> usize {
+        self.slots.purge_unused()
+    }
+
+    pub fn get_stats(&self) -> TextureStats {
+        TextureStats {
+            texture_count: self.slots.textures.len(),
+            cached_paths: self.slots.paths.len(),
+            bytes:
+                self.slots.textures.values()
+                    .map(|data| (data.extent.width * data.extent.height * 4) as usize)
+                    .sum(),
+            cache_hits: self.cache_hits,
+            cache_misses: self.cache_misses,
+        }
+    }
+
+    // `data` is tightly packed RGBA, four bytes per pixel
+    pub fn create_from_rgba(&mut self, width: u32, height: u32, data: &[u8], blend_mode: sdl::BlendMode)
+        -> Result<Texture, Error>
+    {
+        let texture = self.create_sdl_texture(width, height, data, blend_mode)?;
+
+        Ok(self.insert(texture, Extent::new(width as i32, height as i32)))
+    }
+
+    pub fn create_from_image(&mut self, image: &Image<RGBA>, blend_mode: sdl::BlendMode)
+        -> Result<Texture, Error>
+    {
+        self.create_from_rgba(image.width() as u32, image.height() as u32, &image.to_bytes(), blend_mode)
+    }
+
+    // For pixels that change every frame, see update_texture. Starts out
+    // with undefined content.
+    pub fn create_streaming(&mut self, width: u32, height: u32) -> Result<Texture, Error> {
+        let mut texture =
+            self.texture_creator
+                .create_texture_streaming(sdl::PixelFormatEnum::ABGR8888, width, height)
+                .map_err(|e| e.to_string())?;
+        texture.set_blend_mode(sdl::BlendMode::Blend);
+
+        Ok(self.insert(texture, Extent::new(width as i32, height as i32)))
+    }
+
+    fn create_sdl_texture(&self, width: u32, height: u32, data: &[u8], blend_mode: sdl::BlendMode)
+        -> Result<sdl::Texture<'t>, Error>
+    {
+        check_pixel_data(Extent::new(width as i32, height as i32), data)?;
 
         let mut surface =
             sdl::Surface::new(
-                png_img.width as u32,
-                png_img.height as u32,
-                match png_img.depth {
-                    4 => sdl::PixelFormatEnum::ABGR8888,
-                    _ => return Err(Error::UnsupportedPixelFormat)
-                }
-            ).unwrap();
+                width,
+                height,
+                sdl::PixelFormatEnum::ABGR8888
+            )?;
+
+        // Rows of the surface may be padded
+        let pitch = surface.pitch() as usize;
+        let row_length = (width * 4) as usize;
 
         surface.with_lock_mut(
             |buffer| {
-                for (dst, src) in buffer.iter_mut().zip(png_img.data.iter()) {
-                    *dst = *src
+                for (dst, src) in buffer.chunks_mut(pitch).zip(data.chunks(row_length)) {
+                    dst[..row_length].copy_from_slice(src);
                 }
             }
         );
 
-        let mut texture = self.texture_creator.create_texture_from_surface(&surface).unwrap();
+        let mut texture = self.texture_creator.create_texture_from_surface(&surface).map_err(|e| e.to_string())?;
         texture.set_blend_mode(blend_mode);
 
-        let texture_data =
-            TextureData {
-    //            surface: surface,
-                texture
-            };
+        Ok(texture)
+    }
 
-        let index = self.textures.len();
-        self.textures.insert(index, texture_data);
+    // A texture that can be drawn into, see Engine::with_render_target
+    pub fn create_render_target(&mut self, width: u32, height: u32) -> Result<Texture, Error> {
+        let mut texture =
+            self.texture_creator
+                .create_texture_target(sdl::PixelFormatEnum::ARGB8888, width, height)
+                .map_err(|e| e.to_string())?;
+        texture.set_blend_mode(sdl::BlendMode::Blend);
 
-        let out_texture =
-            Texture {
-                index,
-                extent: Extent::new(png_img.width as i32, png_img.height as i32),
-                offset: Offset::new(),
+        Ok(self.insert(texture, Extent::new(width as i32, height as i32)))
+    }
+
+    // Replaces the pixels of the texture, or only those of a sub texture.
+    // `data` is laid out like for create_from_rgba.
+    pub fn update_from_rgba(&mut self, texture: &Texture, data: &[u8]) -> Result<(), Error> {
+        let extent = texture.extent();
+        check_pixel_data(extent, data)?;
+
+        let mut internal = self.get_internal_texture_mut(texture);
+
+        // Textures made from surfaces or as render targets get the format the
+        // renderer prefers
+        let converted : Vec<u8> =
+            match internal.query().format {
+                sdl::PixelFormatEnum::ABGR8888 => data.to_vec(),
+                sdl::PixelFormatEnum::ARGB8888 => {
+                    data.chunks(4).flat_map(|p| vec![p[2], p[1], p[0], p[3]]).collect()
+                },
+                _ => return Err(Error::UnsupportedPixelFormat)
             };
 
-        Ok(out_texture)
+        let rect =
+            sdl::Rect::new(
+                texture.offset().x,
+                texture.offset().y,
+                extent.width as u32,
+                extent.height as u32
+            );
+
+        internal.update(rect, &converted, (extent.width * 4) as usize).map_err(|e| e.to_string())?;
+
+        Ok(())
+    }
+
+    // The image has to be as large as the texture handle
+    pub fn update_texture(&mut self, texture: &Texture, image: &Image<RGBA>) -> Result<(), Error> {
+        check_image_size(texture.extent(), image)?;
+
+        self.update_from_rgba(texture, &image.to_bytes())
+    }
+
+    fn insert(&mut self, texture: sdl::Texture<'t>, extent: Extent) -> Texture {
+        self.slots.insert(texture, extent)
+    }
+
+    pub fn get_internal_texture(&self, texture: &Texture) -> Ref<'_, sdl::Texture<'t>> {
+        self.slots.textures.get(&texture.index).unwrap().texture.borrow()
+    }
+
+    pub fn get_internal_texture_mut(&self, texture: &Texture) -> RefMut<'_, sdl::Texture<'t>> {
+        self.slots.textures.get(&texture.index).unwrap().texture.borrow_mut()
     }
+}
 
-    pub fn get_internal_texture(&self, texture: &Texture) -> &sdl::Texture {
-        &self.textures.get(&texture.index).unwrap().texture
+// Tightly packed RGBA covering the whole extent, see create_from_rgba
+fn check_pixel_data(extent: Extent, data: &[u8]) -> Result<(), Error> {
+    if data.len() != (extent.width * extent.height * 4) as usize {
+        return Err(Error::IncompletePixel);
     }
+
+    Ok(())
+}
+
+// Same error as for raw pixel data, a 2x8 image would pass the length check
+// of a 4x4 texture but come out garbled
+fn check_image_size(extent: Extent, image: &Image<RGBA>) -> Result<(), Error> {
+    if image.width() != extent.width || image.height() != extent.height {
+        return Err(Error::IncompletePixel);
+    }
+
+    Ok(())
+}
+
+fn load_rgba(path: &str) -> Result<(u32, u32, Vec<u8>), Error> {
+    let png_img = match image::load(Path::new(path)) {
+        LoadResult::ImageU8(bytes) => { bytes },
+        LoadResult::ImageF32(_) => panic!("Is float"),
+        _ => return Err(Error::IO { path: Some(path.to_string()) })
+    };
+
+    if png_img.depth != 4 {
+        return Err(Error::UnsupportedPixelFormat);
+    }
+
+    Ok((png_img.width as u32, png_img.height as u32, png_img.data))
+}
+
+#[test]
+fn test_pixel_data_size_check() {
+    let extent = Extent::new(4, 4);
+    let black = RGBA { r: 0, g: 0, b: 0, a: 255 };
+
+    assert!(check_pixel_data(extent, &[0; 64]).is_ok());
+    assert!(matches!(check_pixel_data(extent, &[0; 63]), Err(Error::IncompletePixel)));
+    assert!(matches!(check_pixel_data(extent, &[0; 68]), Err(Error::IncompletePixel)));
+
+    assert!(check_image_size(extent, &Image::new(4, 4, black)).is_ok());
+    assert!(matches!(check_image_size(extent, &Image::new(2, 8, black)), Err(Error::IncompletePixel)));
+    assert!(matches!(check_image_size(extent, &Image::new(4, 3, black)), Err(Error::IncompletePixel)));
+}
+
+#[test]
+fn test_texture_refcount() {
+    let mut slots : TextureSlots<()> = TextureSlots::new();
+    let key = |path: &str| (path.to_string(), sdl::BlendMode::Blend);
+
+    let kept = slots.insert((), Extent::new(4, 4));
+    let dropped = slots.insert((), Extent::new(4, 4));
+    slots.set_path(&kept, key("kept.png"));
+    slots.set_path(&dropped, key("dropped.png"));
+
+    // Sub textures and clones share the handle of their texture
+    let sub = kept.sub_texture(Offset::from_coords(1, 1), Extent::new(2, 2)).unwrap();
+    drop(kept);
+    drop(dropped);
+
+    assert_eq!(slots.purge_unused(), 1);
+    assert_eq!(slots.textures.len(), 1);
+    assert!(!slots.paths.contains_key(&key("dropped.png")));
+    assert!(slots.paths.contains_key(&key("kept.png")));
+
+    // Unloading a texture in use only forgets the path
+    assert!(slots.unload("kept.png"));
+    assert!(!slots.unload("kept.png"));
+    assert!(slots.paths.is_empty());
+    assert_eq!(slots.textures.len(), 1);
+
+    drop(sub);
+    assert_eq!(slots.purge_unused(), 1);
+    assert!(slots.textures.is_empty());
+
+    // Without handles unload frees it right away
+    let unused = slots.insert((), Extent::new(4, 4));
+    slots.set_path(&unused, key("unused.png"));
+    drop(unused);
+
+    assert!(slots.unload("unused.png"));
+    assert!(slots.textures.is_empty());
+    assert_eq!(slots.purge_unused(), 0);
+
+    // Indices of freed textures are not handed out again
+    assert_eq!(slots.insert((), Extent::new(4, 4)).index, 3);
 }
@@ -1,6 +1,6 @@
 use vector::Vec2;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Transform {
     pub translation: Vec2,
     pub scale: f32,
@@ -96,6 +96,23 @@ impl Transform {
         self.translation += p;
     }
 
+    // The transform that applies `child` first and then self
+    pub fn combine(&self, child: &Transform) -> Transform {
+        Transform {
+            translation: self.transform_point(child.translation),
+            scale: self.scale * child.scale,
+            angle: self.angle + child.angle
+        }
+    }
+
+    pub fn inverse(&self) -> Transform {
+        Transform {
+            translation: self.transform_point_inv(Vec2::new()),
+            scale: 1.0 / self.scale,
+            angle: -self.angle
+        }
+    }
+
     pub fn interpolate(&self, other: &Transform, f: f32) -> Transform {
         let mut t = Transform::new();
         t.set_translation((self.translation * (1.0 - f)) + (other.translation * f));
@@ -105,3 +122,23 @@ impl Transform {
     }
 
 }
+
+#[test]
+fn test_combine_and_inverse() {
+    let parent = Transform { translation: Vec2::from_coords(10.0, -5.0), scale: 2.0, angle: 0.5 };
+    let child = Transform { translation: Vec2::from_coords(3.0, 4.0), scale: 0.5, angle: -1.25 };
+    let p = Vec2::from_coords(7.0, 2.0);
+
+    let combined = parent.combine(&child);
+    let expected = parent.transform_point(child.transform_point(p));
+    assert!((combined.transform_point(p) - expected).len() < 0.001);
+    assert!((combined.scale - 1.0).abs() < 0.001);
+    assert!((combined.angle + 0.75).abs() < 0.001);
+
+    let identity = parent.inverse().combine(&parent);
+    assert!(identity.translation.len() < 0.001);
+    assert!((identity.scale - 1.0).abs() < 0.001);
+    assert!(identity.angle.abs() < 0.001);
+
+    assert!((parent.inverse().transform_point(parent.transform_point(p)) - p).len() < 0.001);
+}
@@ -33,6 +33,9 @@ impl GameState for TransitionState {
         let half_duration = self.duration / 2.0;
         if self.time + dt >= half_duration && self.time < half_duration {
             self.current_state = (self.create_target_callback.take().unwrap())(self.current_state, ctx)?;
+
+            // The old state is gone, so are the handles to its textures
+            ctx.get_texture_registry().purge_unused();
             self.current_state = self.current_state.update(ctx, dt)?;
         }
 
@@ -0,0 +1,566 @@
+use std::f32::consts::PI;
+
+use transform::Transform;
+use vector::Vec2;
+use Color;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Easing {
+    Linear,
+    SineIn,
+    SineOut,
+    SineInOut,
+    QuadIn,
+    QuadOut,
+    QuadInOut,
+    CubicIn,
+    CubicOut,
+    CubicInOut,
+    ElasticIn,
+    ElasticOut,
+    ElasticInOut,
+    BackIn,
+    BackOut,
+    BackInOut,
+    BounceIn,
+    BounceOut,
+    BounceInOut,
+}
+
+fn bounce_out(t: f32) -> f32 {
+    let n = 7.5625;
+    let d = 2.75;
+
+    if t < 1.0 / d {
+        n * t * t
+    } else if t < 2.0 / d {
+        let t = t - 1.5 / d;
+        n * t * t + 0.75
+    } else if t < 2.5 / d {
+        let t = t - 2.25 / d;
+        n * t * t + 0.9375
+    } else {
+        let t = t - 2.625 / d;
+        n * t * t + 0.984375
+    }
+}
+
+impl Easing {
+    // Maps linear progress in [0, 1] to eased progress. Elastic and back
+    // curves overshoot, so the result can leave [0, 1] in between.
+    pub fn apply(&self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+
+        let back = 1.70158;
+        let back_in_out = back * 1.525;
+
+        match *self {
+            Easing::Linear => t,
+            Easing::SineIn => 1.0 - (t * PI * 0.5).cos(),
+            Easing::SineOut => (t * PI * 0.5).sin(),
+            Easing::SineInOut => (1.0 - (t * PI).cos()) * 0.5,
+            Easing::QuadIn => t * t,
+            Easing::QuadOut => 1.0 - (1.0 - t) * (1.0 - t),
+            Easing::QuadInOut => {
+                if t < 0.5 { 2.0 * t * t } else { 1.0 - (-2.0 * t + 2.0).powi(2) * 0.5 }
+            },
+            Easing::CubicIn => t * t * t,
+            Easing::CubicOut => 1.0 - (1.0 - t).powi(3),
+            Easing::CubicInOut => {
+                if t < 0.5 { 4.0 * t * t * t } else { 1.0 - (-2.0 * t + 2.0).powi(3) * 0.5 }
+            },
+            Easing::ElasticIn => {
+                if t == 0.0 || t == 1.0 {
+                    t
+                } else {
+                    -(2.0f32).powf(10.0 * t - 10.0) * ((t * 10.0 - 10.75) * (2.0 * PI / 3.0)).sin()
+                }
+            },
+            Easing::ElasticOut => {
+                if t == 0.0 || t == 1.0 {
+                    t
+                } else {
+                    (2.0f32).powf(-10.0 * t) * ((t * 10.0 - 0.75) * (2.0 * PI / 3.0)).sin() + 1.0
+                }
+            },
+            Easing::ElasticInOut => {
+                let c = 2.0 * PI / 4.5;
+                if t == 0.0 || t == 1.0 {
+                    t
+                } else if t < 0.5 {
+                    -((2.0f32).powf(20.0 * t - 10.0) * ((20.0 * t - 11.125) * c).sin()) * 0.5
+                } else {
+                    (2.0f32).powf(-20.0 * t + 10.0) * ((20.0 * t - 11.125) * c).sin() * 0.5 + 1.0
+                }
+            },
+            Easing::BackIn => (back + 1.0) * t * t * t - back * t * t,
+            Easing::BackOut => {
+                let u = t - 1.0;
+                1.0 + (back + 1.0) * u * u * u + back * u * u
+            },
+            Easing::BackInOut => {
+                if t < 0.5 {
+                    ((2.0 * t).powi(2) * ((back_in_out + 1.0) * 2.0 * t - back_in_out)) * 0.5
+                } else {
+                    ((2.0 * t - 2.0).powi(2) * ((back_in_out + 1.0) * (t * 2.0 - 2.0) + back_in_out) + 2.0) * 0.5
+                }
+            },
+            Easing::BounceIn => 1.0 - bounce_out(1.0 - t),
+            Easing::BounceOut => bounce_out(t),
+            Easing::BounceInOut => {
+                if t < 0.5 {
+                    (1.0 - bounce_out(1.0 - 2.0 * t)) * 0.5
+                } else {
+                    (1.0 + bounce_out(2.0 * t - 1.0)) * 0.5
+                }
+            },
+        }
+    }
+}
+
+pub trait Tweenable : Clone + 'static {
+    fn lerp(&self, other: &Self, f: f32) -> Self;
+}
+
+impl Tweenable for f32 {
+    fn lerp(&self, other: &f32, f: f32) -> f32 {
+        self + (other - self) * f
+    }
+}
+
+impl Tweenable for Vec2 {
+    fn lerp(&self, other: &Vec2, f: f32) -> Vec2 {
+        (*self * (1.0 - f)) + (*other * f)
+    }
+}
+
+impl Tweenable for Transform {
+    fn lerp(&self, other: &Transform, f: f32) -> Transform {
+        self.interpolate(other, f)
+    }
+}
+
+impl Tweenable for Color {
+    fn lerp(&self, other: &Color, f: f32) -> Color {
+        let channel = |a: u8, b: u8| (a as f32).lerp(&(b as f32), f).round().clamp(0.0, 255.0) as u8;
+
+        Color::RGBA(
+            channel(self.r, other.r),
+            channel(self.g, other.g),
+            channel(self.b, other.b),
+            channel(self.a, other.a)
+        )
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Repeat {
+    Once,
+    Times(u32),
+    Forever,
+}
+
+// Anything that plays out over time. Tweens, sequences and groups can be
+// nested in each other through this.
+pub trait Tweening {
+    // Returns the part of dt that was not needed to finish
+    fn advance(&mut self, dt: f32) -> f32;
+
+    fn is_finished(&self) -> bool;
+
+    fn restart(&mut self);
+}
+
+// Counts passes for anything that can repeat and calls the completion
+// callback once every pass is done
+struct Playback {
+    repeat: Repeat,
+    passes: u32,
+    finished: bool,
+    on_complete: Option<Box<dyn FnMut()>>,
+}
+
+impl Playback {
+    fn new() -> Playback {
+        Playback {
+            repeat: Repeat::Once,
+            passes: 0,
+            finished: false,
+            on_complete: None,
+        }
+    }
+
+    // Returns true if another pass should be played
+    fn pass_done(&mut self) -> bool {
+        self.passes += 1;
+
+        let again =
+            match self.repeat {
+                Repeat::Once => false,
+                Repeat::Times(n) => self.passes < n,
+                Repeat::Forever => true,
+            };
+
+        if !again {
+            self.finished = true;
+            if let Some(ref mut on_complete) = self.on_complete {
+                on_complete();
+            }
+        }
+
+        again
+    }
+
+    fn restart(&mut self) {
+        self.passes = 0;
+        self.finished = false;
+    }
+}
+
+type UpdateCallback<T> = Box<dyn FnMut(&T)>;
+
+pub struct Tween<T: Tweenable> {
+    from: T,
+    to: T,
+    duration: f32,
+    elapsed: f32,
+    easing: Easing,
+    yoyo: bool,
+    reversed: bool,
+    playback: Playback,
+    on_update: Option<UpdateCallback<T>>,
+}
+
+impl<T: Tweenable> Tween<T> {
+    pub fn new(from: T, to: T, duration: f32) -> Tween<T> {
+        Tween {
+            from,
+            to,
+            duration: duration.max(0.0),
+            elapsed: 0.0,
+            easing: Easing::Linear,
+            yoyo: false,
+            reversed: false,
+            playback: Playback::new(),
+            on_update: None,
+        }
+    }
+
+    pub fn with_easing(mut self, easing: Easing) -> Tween<T> {
+        self.easing = easing;
+        self
+    }
+
+    pub fn with_repeat(mut self, repeat: Repeat) -> Tween<T> {
+        self.playback.repeat = repeat;
+        self
+    }
+
+    // Every other pass plays backwards
+    pub fn with_yoyo(mut self, yoyo: bool) -> Tween<T> {
+        self.yoyo = yoyo;
+        self
+    }
+
+    pub fn on_update<F: FnMut(&T) + 'static>(mut self, callback: F) -> Tween<T> {
+        self.on_update = Some(Box::new(callback));
+        self
+    }
+
+    pub fn on_complete<F: FnMut() + 'static>(mut self, callback: F) -> Tween<T> {
+        self.playback.on_complete = Some(Box::new(callback));
+        self
+    }
+
+    pub fn progress(&self) -> f32 {
+        let t = if self.duration > 0.0 { self.elapsed / self.duration } else { 1.0 };
+
+        if self.reversed { 1.0 - t } else { t }
+    }
+
+    pub fn value(&self) -> T {
+        self.from.lerp(&self.to, self.easing.apply(self.progress()))
+    }
+}
+
+impl<T: Tweenable> Tweening for Tween<T> {
+    fn advance(&mut self, dt: f32) -> f32 {
+        let mut dt = dt;
+
+        while !self.playback.finished {
+            let remaining = self.duration - self.elapsed;
+
+            if dt < remaining {
+                self.elapsed += dt;
+                dt = 0.0;
+                break;
+            }
+
+            dt -= remaining;
+            self.elapsed = self.duration;
+
+            // The last pass keeps its final value
+            if !self.playback.pass_done() {
+                break;
+            }
+
+            self.elapsed = 0.0;
+            if self.yoyo {
+                self.reversed = !self.reversed;
+            }
+
+            if self.duration <= 0.0 {
+                // Nothing to play, so don't spin on a repeating tween
+                break;
+            }
+        }
+
+        let value = self.value();
+        if let Some(ref mut on_update) = self.on_update {
+            on_update(&value);
+        }
+
+        dt
+    }
+
+    fn is_finished(&self) -> bool {
+        self.playback.finished
+    }
+
+    fn restart(&mut self) {
+        self.elapsed = 0.0;
+        self.reversed = false;
+        self.playback.restart();
+    }
+}
+
+// Plays its parts one after the other
+pub struct TweenSequence {
+    parts: Vec<Box<dyn Tweening>>,
+    current: usize,
+    playback: Playback,
+}
+
+impl Default for TweenSequence {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TweenSequence {
+    pub fn new() -> TweenSequence {
+        TweenSequence {
+            parts: Vec::new(),
+            current: 0,
+            playback: Playback::new(),
+        }
+    }
+
+    pub fn then<T: Tweening + 'static>(mut self, part: T) -> TweenSequence {
+        self.parts.push(Box::new(part));
+        self
+    }
+
+    pub fn with_repeat(mut self, repeat: Repeat) -> TweenSequence {
+        self.playback.repeat = repeat;
+        self
+    }
+
+    pub fn on_complete<F: FnMut() + 'static>(mut self, callback: F) -> TweenSequence {
+        self.playback.on_complete = Some(Box::new(callback));
+        self
+    }
+}
+
+impl Tweening for TweenSequence {
+    fn advance(&mut self, dt: f32) -> f32 {
+        let mut dt = dt;
+        // Time left when the last whole pass started, the first one may have
+        // started in an earlier update
+        let mut pass_start = None;
+
+        while !self.playback.finished {
+            if self.current < self.parts.len() {
+                dt = self.parts[self.current].advance(dt);
+
+                if !self.parts[self.current].is_finished() {
+                    break;
+                }
+
+                self.current += 1;
+                continue;
+            }
+
+            if !self.playback.pass_done() || self.parts.is_empty() {
+                break;
+            }
+
+            if pass_start.is_some_and(|start| dt >= start) {
+                // A whole pass took no time, so don't spin on a repeating sequence
+                break;
+            }
+            pass_start = Some(dt);
+
+            self.current = 0;
+            for part in self.parts.iter_mut() {
+                part.restart();
+            }
+        }
+
+        dt
+    }
+
+    fn is_finished(&self) -> bool {
+        self.playback.finished
+    }
+
+    fn restart(&mut self) {
+        self.current = 0;
+        self.playback.restart();
+        for part in self.parts.iter_mut() {
+            part.restart();
+        }
+    }
+}
+
+// Plays its parts at the same time and finishes with the longest one
+pub struct TweenGroup {
+    parts: Vec<Box<dyn Tweening>>,
+    playback: Playback,
+}
+
+impl Default for TweenGroup {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TweenGroup {
+    pub fn new() -> TweenGroup {
+        TweenGroup {
+            parts: Vec::new(),
+            playback: Playback::new(),
+        }
+    }
+
+    pub fn with<T: Tweening + 'static>(mut self, part: T) -> TweenGroup {
+        self.parts.push(Box::new(part));
+        self
+    }
+
+    pub fn with_repeat(mut self, repeat: Repeat) -> TweenGroup {
+        self.playback.repeat = repeat;
+        self
+    }
+
+    pub fn on_complete<F: FnMut() + 'static>(mut self, callback: F) -> TweenGroup {
+        self.playback.on_complete = Some(Box::new(callback));
+        self
+    }
+}
+
+impl Tweening for TweenGroup {
+    fn advance(&mut self, dt: f32) -> f32 {
+        let mut dt = dt;
+        let mut pass_start = None;
+
+        while !self.playback.finished {
+            let mut left_over = dt;
+            for part in self.parts.iter_mut().filter(|p| !p.is_finished()) {
+                left_over = left_over.min(part.advance(dt));
+            }
+
+            if !self.parts.iter().all(|p| p.is_finished()) {
+                return 0.0;
+            }
+
+            dt = left_over;
+
+            if !self.playback.pass_done() || self.parts.is_empty() {
+                break;
+            }
+
+            if pass_start.is_some_and(|start| dt >= start) {
+                break;
+            }
+            pass_start = Some(dt);
+
+            for part in self.parts.iter_mut() {
+                part.restart();
+            }
+        }
+
+        dt
+    }
+
+    fn is_finished(&self) -> bool {
+        self.playback.finished
+    }
+
+    fn restart(&mut self) {
+        self.playback.restart();
+        for part in self.parts.iter_mut() {
+            part.restart();
+        }
+    }
+}
+
+#[test]
+fn test_easing_end_points() {
+    let easings = [
+        Easing::Linear, Easing::SineIn, Easing::SineOut, Easing::SineInOut,
+        Easing::QuadIn, Easing::QuadOut, Easing::QuadInOut,
+        Easing::CubicIn, Easing::CubicOut, Easing::CubicInOut,
+        Easing::ElasticIn, Easing::ElasticOut, Easing::ElasticInOut,
+        Easing::BackIn, Easing::BackOut, Easing::BackInOut,
+        Easing::BounceIn, Easing::BounceOut, Easing::BounceInOut,
+    ];
+
+    for easing in easings.iter() {
+        assert!(easing.apply(0.0).abs() < 0.001, "{:?} does not start at 0", easing);
+        assert!((easing.apply(1.0) - 1.0).abs() < 0.001, "{:?} does not end at 1", easing);
+    }
+}
+
+#[test]
+fn test_yoyo_tween_returns_to_start() {
+    let mut tween = Tween::new(0.0f32, 10.0, 1.0).with_yoyo(true).with_repeat(Repeat::Times(2));
+
+    tween.advance(1.5);
+    assert!((tween.value() - 5.0).abs() < 0.001);
+    assert!(!tween.is_finished());
+
+    let left_over = tween.advance(1.0);
+    assert!(tween.is_finished());
+    assert!(tween.value().abs() < 0.001);
+    assert!((left_over - 0.5).abs() < 0.001);
+}
+
+#[test]
+fn test_zero_length_containers_do_not_spin() {
+    let mut sequence =
+        TweenSequence::new()
+            .then(Tween::new(0.0f32, 1.0, 0.0))
+            .then(Tween::new(1.0f32, 2.0, 0.0))
+            .with_repeat(Repeat::Forever);
+
+    assert!((sequence.advance(1.0) - 1.0).abs() < 0.001);
+    assert!(!sequence.is_finished());
+
+    let mut group =
+        TweenGroup::new()
+            .with(Tween::new(0.0f32, 1.0, 0.0))
+            .with_repeat(Repeat::Times(u32::MAX));
+
+    assert!((group.advance(1.0) - 1.0).abs() < 0.001);
+    assert!(!group.is_finished());
+
+    // Parts with a length still repeat within one advance
+    let mut sequence =
+        TweenSequence::new()
+            .then(Tween::new(0.0f32, 1.0, 0.25))
+            .with_repeat(Repeat::Times(3));
+
+    assert!((sequence.advance(1.0) - 0.25).abs() < 0.001);
+    assert!(sequence.is_finished());
+}
@@ -0,0 +1,139 @@
+use rect::Rect2D;
+use transform::Transform;
+use vector::Vec2;
+
+// A named camera drawing into part of the window. The area is given in
+// fractions of the window, so split-screen layouts survive going full
+// screen. Viewports are drawn in the order they were added, so a minimap
+// added last ends up on top.
+pub struct Viewport {
+    name: String,
+    area: Rect2D,
+    camera: Transform,
+    enabled: bool,
+}
+
+impl Viewport {
+    pub fn new(name: &str, area: Rect2D, camera: Transform) -> Viewport {
+        Viewport {
+            name: name.to_string(),
+            area,
+            camera,
+            enabled: true,
+        }
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn get_area(&self) -> Rect2D {
+        self.area
+    }
+
+    pub fn set_area(&mut self, area: Rect2D) {
+        self.area = area;
+    }
+
+    pub fn get_camera(&self) -> &Transform {
+        &self.camera
+    }
+
+    pub fn get_camera_mut(&mut self) -> &mut Transform {
+        &mut self.camera
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    // Area of the window in pixels
+    pub fn get_screen_rect(&self, screen_bounds: Rect2D) -> Rect2D {
+        let size = screen_bounds.max - screen_bounds.min;
+
+        Rect2D::new(
+            screen_bounds.min + self.area.min * size,
+            screen_bounds.min + self.area.max * size
+        )
+    }
+
+    // Area of the world this viewport shows
+    pub fn get_visible_area(&self, screen_bounds: Rect2D) -> Rect2D {
+        let rect = self.get_screen_rect(screen_bounds);
+        let half_size = (rect.max - rect.min) * 0.5;
+
+        let mut bounds = Rect2D::new(Vec2::new() - half_size, half_size);
+        bounds *= self.camera.get_scale();
+        bounds += self.camera.get_translation();
+
+        bounds
+    }
+
+    pub fn screen_to_world(&self, screen_bounds: Rect2D, p: Vec2) -> Vec2 {
+        let rect = self.get_screen_rect(screen_bounds);
+        self.camera.transform_point(p - rect.center())
+    }
+}
+
+// The enabled viewport under a point in pixels. Later viewports are drawn on
+// top, so they win where viewports overlap.
+pub fn viewport_at(viewports: &[Viewport], screen_bounds: Rect2D, p: Vec2) -> Option<&Viewport> {
+    viewports.iter()
+        .rev()
+        .filter(|v| v.is_enabled())
+        .find(|v| v.get_screen_rect(screen_bounds).contains(p))
+}
+
+// Areas for common layouts, to pass to Engine::add_viewport
+pub fn split_horizontal() -> (Rect2D, Rect2D) {
+    (
+        Rect2D::new(Vec2::from_coords(0.0, 0.0), Vec2::from_coords(0.5, 1.0)),
+        Rect2D::new(Vec2::from_coords(0.5, 0.0), Vec2::from_coords(1.0, 1.0))
+    )
+}
+
+pub fn split_vertical() -> (Rect2D, Rect2D) {
+    (
+        Rect2D::new(Vec2::from_coords(0.0, 0.0), Vec2::from_coords(1.0, 0.5)),
+        Rect2D::new(Vec2::from_coords(0.0, 0.5), Vec2::from_coords(1.0, 1.0))
+    )
+}
+
+#[test]
+fn test_viewport_mapping() {
+    let screen_bounds = Rect2D::new(Vec2::new(), Vec2::from_coords(200.0, 100.0));
+    let (left_area, right_area) = split_horizontal();
+
+    let mut camera = Transform::new();
+    camera.set_translation(Vec2::from_coords(1000.0, 0.0));
+    camera.set_scale(2.0);
+
+    let left = Viewport::new("left", left_area, Transform::new());
+    let right = Viewport::new("right", right_area, camera);
+    let minimap = Viewport::new("minimap", Rect2D::new(Vec2::from_coords(0.4, 0.0), Vec2::from_coords(0.6, 0.2)), Transform::new());
+
+    let rect = right.get_screen_rect(screen_bounds);
+    assert_eq!((rect.min, rect.max), (Vec2::from_coords(100.0, 0.0), Vec2::from_coords(200.0, 100.0)));
+
+    // The middle of a viewport is where its camera looks, zoom scales the rest
+    assert_eq!(left.screen_to_world(screen_bounds, Vec2::from_coords(50.0, 50.0)), Vec2::new());
+    assert_eq!(right.screen_to_world(screen_bounds, Vec2::from_coords(150.0, 50.0)), Vec2::from_coords(1000.0, 0.0));
+    assert_eq!(right.screen_to_world(screen_bounds, Vec2::from_coords(160.0, 40.0)), Vec2::from_coords(1020.0, -20.0));
+
+    let mut viewports = vec![left, right, minimap];
+    let name_at = |viewports: &[Viewport], x: f32, y: f32| {
+        viewport_at(viewports, screen_bounds, Vec2::from_coords(x, y)).map(|v| v.get_name().to_string())
+    };
+
+    assert_eq!(name_at(&viewports, 10.0, 50.0), Some("left".to_string()));
+    assert_eq!(name_at(&viewports, 190.0, 50.0), Some("right".to_string()));
+    assert_eq!(name_at(&viewports, 100.0, 10.0), Some("minimap".to_string()));
+
+    viewports[2].set_enabled(false);
+    assert_eq!(name_at(&viewports, 90.0, 10.0), Some("left".to_string()));
+    assert_eq!(name_at(&viewports, 100.0, 150.0), None);
+}
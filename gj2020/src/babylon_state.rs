@@ -7,12 +7,15 @@ struct SpewBloodData {
     origin: Vec2
 }
 
+// Sent by a victim to itself once it has been stuck in one spot for a while
+struct TurnAround;
+
 struct Victim {
     animated_sprite: AnimatedSprite,
     transform: Transform,
     velocity: Vec2,
     direction: f32,
-    standing_still: f32,
+    turn_scheduled: bool,
     standing_position: Vec2,
     shape: Rc<dyn CollisionShape>,
     dead: bool,
@@ -21,10 +24,7 @@ struct Victim {
 }
 
 impl Victim {
-    pub fn new(ctx: &mut Engine) -> Result<Victim, Error> {
-         let tr = ctx.get_texture_registry();
-         let texture = tr.load("assets/images/tower/victim.png")?;
-
+    pub fn new(texture: Texture) -> Result<Victim, Error> {
 
          let extent = Extent::new(64, 128);
          let sprite = AnimatedSprite::new(extent, texture)?;
@@ -40,7 +40,7 @@ impl Victim {
                 velocity: Vec2::new(),
                 shape,
                 direction: 1.0,
-                standing_still: 0.0,
+                turn_scheduled: false,
                 standing_position: Vec2::new(),
                 dead: false,
                 friction: 0.0,
@@ -71,7 +71,7 @@ impl Victim {
 
             let data = SpewBloodData { origin: self.transform.get_translation() };
 
-            self.outgoing_events.push(EventType::Custom { data: Rc::new(data) });
+            self.outgoing_events.push(EventType::typed(data));
         }
     }
 }
@@ -90,27 +90,31 @@ impl GameObject for Victim {
 
         let target_velocity = Vec2::from_coords(self.direction * 100.0, 0.0);
 
+        let mut moved = false;
+
         if (self.standing_position - self.transform.get_translation()).len() > 5.0 {
             self.standing_position = self.transform.get_translation();
-            self.standing_still = 0.0;
+            moved = true;
         }
 
-        self.standing_still += dt;
-
         let bounds = ctx.get_visible_area();
 
-        if self.direction > 0.0 && self.transform.get_translation().x > bounds.max.x {
-            self.standing_still = 1000.0;
-        }
+        let out_of_bounds =
+            (self.direction > 0.0 && self.transform.get_translation().x > bounds.max.x) ||
+            (self.direction < 0.0 && self.transform.get_translation().x < bounds.min.x);
 
-        if self.direction < 0.0 && self.transform.get_translation().x < bounds.min.x {
-            self.standing_still = 1000.0;
+        if out_of_bounds {
+            self.direction *= -1.0;
         }
 
-        if self.standing_still > 1.0 {
-            self.direction *= -1.0;
-            self.standing_still = 0.0;
-            println!("Standing still!");
+        // Turns around after standing still for a second, moving pushes the
+        // turn back
+        if moved || out_of_bounds || !self.turn_scheduled {
+            if let Some(id) = event_mailbox.get_sender_id() {
+                event_mailbox.cancel_scheduled_events();
+                event_mailbox.schedule_event(1.0, EventType::typed(TurnAround), EventReceiver::Addressed { object_id: id });
+                self.turn_scheduled = true;
+            }
         }
 
         if self.direction > 0.0 {
@@ -137,6 +141,15 @@ impl GameObject for Victim {
     fn get_physical_object_mut(&mut self) -> Option<&mut dyn PhysicalObject> { Some(self) }
 
     fn on_event(&mut self, event: EventType, _sender: Option<SceneObjectId>) -> bool {
+        if event.is::<TurnAround>() {
+            self.turn_scheduled = false;
+            if !self.dead {
+                self.direction *= -1.0;
+                println!("Standing still!");
+            }
+            return true;
+        }
+
         match event {
             EventType::Collide { force } => {
                 if force.y > 0.5 {
@@ -180,12 +193,51 @@ impl PhysicalObject for Victim {
     fn get_dst_mask(&self) -> u32 { 1 }
 }
 
+// Rigid bodies for the blocks on layer 1, decoration for everything else
+fn spawn_tower(scene: &mut Scene, level: &Level2D) -> Vec<SceneObjectId> {
+    let mut tower_ids = Vec::new();
+
+    for instance in level.level_instance.object_instances.iter() {
+        let object_type = level.level_instance.object_types.get(instance.object_id as usize).unwrap();
+
+        let texture = level.object_textures.get(&object_type.file).unwrap().clone();
+
+        let mut transform = Transform::new();
+        transform.set_translation(instance.position);
+        transform.set_angle(instance.rotation);
+        transform.set_scale(instance.scale);
+
+        if object_type.layers.contains(&1) {
+            let mut rigid_body = RigidBody::new(texture, ShapeFit::Rectangle(1.0));
+            rigid_body.set_transform(transform);
+            rigid_body.set_friction(100.0);
+            rigid_body.set_scale(instance.scale);
+            if !object_type.fixed {
+                rigid_body.set_mass(1.0);
+            }
+            rigid_body.set_inertia(100.0);
+            tower_ids.push(scene.add_object(rigid_body));
+        } else {
+            let mut object = DecorationObject::new(texture);
+            object.set_transform(transform);
+            object.set_z_index(-1);
+            tower_ids.push(scene.add_object(object));
+        }
+    }
+
+    tower_ids
+}
+
 pub struct BabylonState {
     scene: Scene,
+    level: Level2D,
+    // Built from the level, so they are replaced when it is reloaded
+    tower_ids: Vec<SceneObjectId>,
     cannon_ball_texture: Texture,
     hub_state: Option<Box<dyn GameState>>,
-    blood_texture: Texture,
+    blood_emitter: ParticleEmitter,
     remaining_victims: i32,
+    camera: CameraController,
 }
 
 
@@ -198,38 +250,12 @@ impl BabylonState {
 
         let tr = ctx.get_texture_registry();
         let cannon_ball_texture = tr.load("assets/images/cannon_ball.png")?;
-        let blood_texture = tr.load("assets/images/tower/blood.png")?;
+        let blood_emitter = ParticleEmitter::load(ctx, "assets/particles/blood.json")?;
 
         let force = LinearForce::new(Vec2::from_coords(0.0, 400.0));
         scene.add_force(force);
 
-        for instance in level.level_instance.object_instances.iter() {
-            let object_type = level.level_instance.object_types.get(instance.object_id as usize).unwrap();
-
-            let texture = level.object_textures.get(&object_type.file).unwrap().clone();
-
-            let mut transform = Transform::new();
-            transform.set_translation(instance.position);
-            transform.set_angle(instance.rotation);
-            transform.set_scale(instance.scale);
-
-            if object_type.layers.contains(&1) {
-                let mut rigid_body = RigidBody::new(texture, ShapeFit::Rectangle(1.0));
-                rigid_body.set_transform(transform);
-                rigid_body.set_friction(100.0);
-                rigid_body.set_scale(instance.scale);
-                if !object_type.fixed {
-                    rigid_body.set_mass(1.0);
-                }
-                rigid_body.set_inertia(100.0);
-                scene.add_object(rigid_body);
-            } else {
-                let mut object = DecorationObject::new(texture);
-                object.set_transform(transform);
-                object.set_z_index(-1);
-                scene.add_object(object);
-            }
-        }
+        let tower_ids = spawn_tower(&mut scene, &level);
 
         // let bounds = ctx.get_visible_area() * 2.0;
 
@@ -238,13 +264,26 @@ impl BabylonState {
 
         let mut total_victim_count = 0;
 
+        // Every victim gets the sprite sheet as it is or one of a few
+        // recoloured ones
+        let victim_filename = "assets/images/tower/victim.png";
+        let victim_palette = Palette::load(victim_filename)?;
+        let mut palettes = PaletteCache::new();
+        let mut victim_textures = vec![ctx.get_texture_registry().load(victim_filename)?];
+        for hue in [90.0, 180.0, 270.0].iter() {
+            let variant = hue.to_string();
+            palettes.add_variant(&variant, PaletteSwap::new(&victim_palette, &victim_palette.with_hue_shift(*hue)));
+            victim_textures.push(palettes.get(ctx, victim_filename, &variant)?);
+        }
+
         for _ in 0..10 {
             let r = rng.gen::<f32>();
             let x = (r * -1400.0) + ((1.0 - r) * -800.0);
 
             println!("x = {}", x);
 
-            let mut victim = Victim::new(ctx)?;
+            let texture = victim_textures[rng.gen_range(0..victim_textures.len())].clone();
+            let mut victim = Victim::new(texture)?;
             victim.set_position(Vec2::from_coords(x, 540.0));
             scene.add_object(victim);
 
@@ -257,7 +296,8 @@ impl BabylonState {
 
             println!("x = {}", x);
 
-            let mut victim = Victim::new(ctx)?;
+            let texture = victim_textures[rng.gen_range(0..victim_textures.len())].clone();
+            let mut victim = Victim::new(texture)?;
             victim.set_position(Vec2::from_coords(x, 540.0));
             scene.add_object(victim);
 
@@ -269,10 +309,19 @@ impl BabylonState {
         let state =
             BabylonState {
                 scene,
+                level,
+                tower_ids,
                 cannon_ball_texture,
                 hub_state: Some(hub_state),
-                blood_texture,
-                remaining_victims: total_victim_count
+                blood_emitter,
+                remaining_victims: total_victim_count,
+                // Keeps the living victims in view, never closer than the
+                // whole tower
+                camera: CameraController::new()
+                    .with_zoom(2.0)
+                    .with_follow_speed(2.0)
+                    .with_zoom_speed(1.0)
+                    .with_zoom_to_fit(200.0, 2.0, 3.0),
             };
 
 
@@ -322,48 +371,43 @@ impl BabylonState {
         self.remaining_victims -= 1;
 
         println!("Remaining victims: {:?}", self.remaining_victims);
-        use self::rand::Rng;
-        let mut rng = rand::thread_rng();
-
-        for _i in 0..20 {
-            let mut rigid_body =
-                RigidBody::new(
-                    self.blood_texture.clone(),
-                    ShapeFit::Rectangle(0.5)
-                );
-
-            let speed = 400.0;
-            let mut velocity = Vec2::new();
-            velocity.x = (rng.gen::<f32>() - 0.5) * speed;
-            velocity.y = (rng.gen::<f32>() - 1.0) * speed;
 
-            rigid_body.set_mass(0.01);
-            rigid_body.set_inertia(100.0);
-            rigid_body.set_position(origin);
-            rigid_body.set_velocity(velocity);
-            rigid_body.set_dst_mask(5);
-            rigid_body.set_src_mask(5);
-            self.scene.add_object(rigid_body);
-        }
+        let mut emitter = self.blood_emitter.clone();
+        emitter.set_position(origin);
+        self.scene.add_emitter(emitter);
     }
 }
 
 
 impl GameState for BabylonState {
     fn update(mut self: Box<Self>, ctx: &mut Engine, dt: f32) -> Result<Box<dyn GameState>, Error> {
+        // The tower is built again from scratch, victims stay where they are
+        if ctx.assets_changed(self.level.get_source_files()) {
+            self.level.reload(ctx);
+
+            for id in self.tower_ids.drain(..) {
+                self.scene.remove_object(id);
+            }
+            self.tower_ids = spawn_tower(&mut self.scene, &self.level);
+        }
+
         let events = self.scene.update(ctx, None, dt);
 
+        let victims: Vec<Vec2> =
+            self.scene.iter_of_type::<Victim>()
+                .filter(|(_, victim)| !victim.dead)
+                .map(|(_, victim)| victim.transform.get_translation())
+                .collect();
+
+        self.camera.set_targets(&victims);
+        self.camera.update(ctx, dt);
+
         for event in events.into_iter() {
-            match event.event_type {
-                EventType::Custom { data } => {
-                    if let Ok(data) = data.downcast::<SpewBloodData>() {
-                        println!("Got spew blood data");
-                        self.spew_blood(data.origin);
-                    }
-                },
-                _ => {
-                    println!("Unknown event received");
-                }
+            if let Some(data) = event.event_type.downcast_ref::<SpewBloodData>() {
+                println!("Got spew blood data");
+                self.spew_blood(data.origin);
+            } else {
+                println!("Unknown event received");
             }
         }
 
@@ -390,8 +434,8 @@ impl GameState for BabylonState {
     fn get_background_color(&self) -> Color { Color::RGB(215, 224, 255) }
 
     fn draw(&mut self, ctx: &mut Engine, _dt: f32) -> Result<(), Error> {
-        ctx.set_camera_position(Vec2::from_coords(0.0, 0.0));
-        ctx.set_camera_zoom(2.0);
+        ctx.set_camera_position(self.camera.get_position());
+        ctx.set_camera_zoom(self.camera.get_zoom());
         self.scene.render(ctx);
 
         Ok(())
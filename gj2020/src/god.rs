@@ -1,10 +1,16 @@
 use std::rc::Rc;
 use engine::prelude::*;
 
+// By walking and then direction: up, right, down, left
+const CLIPS: [&str; 8] = [
+    "idle_up", "idle_right", "idle_down", "idle_left",
+    "walk_up", "walk_right", "walk_down", "walk_left",
+];
+
 pub struct God {
     controller: AxisController,
     interact_trigger: Trigger,
-    sprite: AggregatedAnimatedSprite,
+    sprite: SpriteAnimation,
     transform: Transform,
     velocity: Vec2,
     direction: i32,
@@ -14,18 +20,14 @@ pub struct God {
 
 impl God {
     pub fn new(ctx: &mut Engine) -> Result<God, Error> {
-        let tr = ctx.get_texture_registry();
-        let texture = tr.load("assets/images/God/god.png")?;
-
-        let walk_texture = texture.sub_texture(Offset::from_coords(240, 0), Extent::new(240 * 2, 480 * 4))?;
-        let walk_sprite = AnimatedSprite::new(Extent::new(240, 480), walk_texture)?;
+        let sprite = SpriteAnimation::load(ctx, "assets/animations/god.json")?;
 
-        let idle_texture = texture.sub_texture(Offset::from_coords(0, 0), Extent::new(240, 480 * 4))?;
-        let idle_sprite = AnimatedSprite::new(Extent::new(240, 480), idle_texture)?;
-
-        let mut sprite = AggregatedAnimatedSprite::new();
-        sprite.add(idle_sprite);
-        sprite.add(walk_sprite);
+        // Checked once, so playing them in the update can not fail
+        for clip in CLIPS.iter() {
+            if !sprite.has_clip(clip) {
+                return Err(Error::FatalError(format!("The god animation has no {} clip", clip)));
+            }
+        }
 
         let collision_size = Vec2::from_coords(200.0, 80.0);
         let rect = Rect2D::centered_rectangle(collision_size);
@@ -78,7 +80,7 @@ impl GameObject for God {
                 false
             };
 
-       let mode = self.direction + if is_walking { 4 } else { 0 };
+        let clip = self.direction as usize + if is_walking { 4 } else { 0 };
 
         let mut sprite_transform = self.transform.clone();
         let collision_height = self.collision_size.y;
@@ -91,7 +93,7 @@ impl GameObject for God {
             )
         );
 
-        self.sprite.set_mode(mode);
+        self.sprite.play(CLIPS[clip]).ok();
         self.sprite.set_transform(&sprite_transform);
         self.sprite.step_time(dt * self.velocity.len() * 0.01);
 
@@ -28,7 +28,8 @@ impl GameObject for Background {
         else {
             _factor = y_factor;
         }
-        self.transform.set_translation(_ctx.screen_to_world((_ctx.get_width()/2) as i32, (_ctx.get_height()/2) as i32));
+        // The background layer does not move with the camera
+        self.transform.set_translation(Vec2::new());
         self.transform.set_scale(_factor);
         true
     }
@@ -44,8 +45,6 @@ impl GameObject for Background {
     fn on_event(&mut self, _event: EventType, _sender: Option<SceneObjectId>) -> bool {
         false
     }
-
-    fn get_z_index(&self) -> i32 { -69 }
 }
 pub struct Demon {
     texture: AggregatedAnimatedSprite,
@@ -129,18 +128,24 @@ pub struct HellState {
 }
 
 pub struct Club {
-    texture: AggregatedAnimatedSprite,
+    sprite: SpriteAnimation,
+    transform: Transform,
     down: bool,
 }
 
 impl Club {
-    fn new(tex: AggregatedAnimatedSprite) -> Club {
-        let mut club = Club { texture: tex, down: false };
-        club.texture.set_scale(1.0);
-        club
+    fn new(sprite: SpriteAnimation) -> Result<Club, Error> {
+        for clip in ["up", "swing"].iter() {
+            if !sprite.has_clip(clip) {
+                return Err(Error::FatalError(format!("The club animation has no {} clip", clip)));
+            }
+        }
+
+        Ok(Club { sprite, transform: Transform::new(), down: false })
     }
     fn set_translation(&mut self, pos: Vec2) {
-        self.texture.set_position(pos);
+        self.transform.set_translation(pos);
+        self.sprite.set_transform(&self.transform);
     }
 }
 
@@ -149,35 +154,45 @@ impl GameObject for Club {
         self.set_translation(_ctx.get_mouse_position().position);
         match _ctx.get_mouse_drag_state() {
             Some(_x) => {
-                    self.texture.set_mode(1);
                     if !self.down {
                         self.down = true;
-                        let origin = _ctx.get_mouse_position().position;
-                        let max_distance = Some(100.0);
-                        _event_mailbox.submit_event(EventType::Attack{damage:1.0}, EventReceiver::Nearby{ origin, max_distance });
+                        self.sprite.play("swing").ok();
                     }
             }
             None => {
                 self.down = false;
-                self.texture.set_mode(0);
+                self.sprite.play("up").ok();
+            }
+        }
+        self.sprite.step_time(_dt);
+
+        // The swing lands on the "hit" frame, see assets/animations/club.json
+        for event in self.sprite.take_events() {
+            if event.name == "hit" {
+                let origin = self.transform.get_translation();
+                let max_distance = Some(100.0);
+                _event_mailbox.submit_event(EventType::Attack{damage:1.0}, EventReceiver::Nearby{ origin, max_distance });
             }
         }
         true
     }
 
     fn render(&self, _ctx: &mut DrawContext) {
-        self.texture.draw(_ctx);
+        self.sprite.draw(_ctx);
     }
 
     fn get_physical_object(&self) -> Option<&dyn PhysicalObject> { None }
 
     fn get_physical_object_mut(&mut self) -> Option<&mut dyn PhysicalObject> { None }
 
+    // No physics, but the club can still be attached to other objects
+    fn get_scene_transform(&self) -> Option<&Transform> { Some(&self.transform) }
+
+    fn get_scene_transform_mut(&mut self) -> Option<&mut Transform> { Some(&mut self.transform) }
+
     fn on_event(&mut self, _event: EventType, _sender: Option<SceneObjectId>) -> bool {
         false
     }
-
-    fn get_z_index(&self) -> i32 { 69 }
 }
 
 impl HellState {
@@ -185,25 +200,21 @@ impl HellState {
         let mut scene = Scene::new();
 
         _ctx.replace_sound(AudioLibrary::Hell, 0, -1)?;
+        let club = Club::new(SpriteAnimation::load(_ctx, "assets/animations/club.json")?)?;
+
         let tr = _ctx.get_texture_registry();
         let demon_texture = tr.load("assets/images/Demon/Demon.png")?;
         let demon1_texture = tr.load("assets/images/Demon/Demon1.png")?;
 
-        let club_down_texture = tr.load("assets/images/Club/Club_dwn.png")?;
-        let club_down_sprite = AnimatedSprite::new(Extent::new(240,240), club_down_texture)?;
-
-        let club_up_texture = tr.load("assets/images/Club/Club_up.png")?;
-        let club_up_sprite = AnimatedSprite::new(Extent::new(240,240), club_up_texture)?;
-
-        let mut club_sprite = AggregatedAnimatedSprite::new();
-        club_sprite.add(club_up_sprite);
-        club_sprite.add(club_down_sprite);
-
-        let club = Club::new( club_sprite );
-        scene.add_object(club);
+        let club_id = scene.add_object(club);
         let background_texture = tr.load("assets/images/Hell.png")?;
         let background = Background::new(background_texture);
-        scene.add_object(background);
+        let background_id = scene.add_object(background);
+
+        scene.add_render_layer(RenderLayer::new("background", -1).with_parallax(0.0));
+        scene.add_render_layer(RenderLayer::new("cursor", 1));
+        scene.set_render_layer(background_id, "background");
+        scene.set_render_layer(club_id, "cursor");
         let state =
             HellState {
                 scene,
@@ -24,6 +24,8 @@ pub struct HubState {
     space_trigger: MinigameTrigger,
 }
 
+const MINIGAME_BLOCKS : [&str; 5] = ["babylon", "noah", "snek", "hell", "space"];
+
 impl HubState {
 
     pub fn create_minigame_for_block(ctx: &mut Engine, block_name: &str, level: &mut Level) -> Minigame {
@@ -92,6 +94,17 @@ impl HubState {
 
 impl GameState for HubState {
     fn update(mut self: Box<Self>, ctx: &mut Engine, dt: f32) -> Result<Box<dyn GameState>, Error> {
+        if ctx.assets_changed(self.level.get_source_files()) {
+            self.level.reload(ctx);
+
+            // The minigames already took their tiles out of the level
+            for block_name in MINIGAME_BLOCKS.iter() {
+                if let Some(tile_id) = self.level.special_blocks.remove(*block_name) {
+                    self.level.objects.take_tile_with_id(tile_id);
+                }
+            }
+        }
+
         self.scene.update(ctx, Some(&self.level.objects), dt);
 
         if !ctx.is_playing(0) {
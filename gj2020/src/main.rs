@@ -33,7 +33,6 @@ pub mod hell_state;
 // Space modules
 pub mod space_state;
 pub mod celestial_body;
-pub mod smooth_transform;
 
 struct GodSend { }
 
@@ -45,6 +44,14 @@ impl GameInterface for GodSend {
     fn create_starting_state(ctx: &mut Engine)
         -> Result<Box<dyn GameState>, Error>
     {
+        if std::env::var("HOT_RELOAD").is_ok() {
+            ctx.enable_hot_reload();
+        }
+
+        if std::env::var("SCREENSHOTS").is_ok() {
+            ctx.get_frame_capture().set_screenshot_key(Some(Keycode::F12));
+        }
+
         let mut sounds = HashMap::new();
         sounds.insert(AudioLibrary::HubWorld, "assets/music/godstheme.wav");
         sounds.insert(AudioLibrary::Hell, "assets/music/hell.wav");
@@ -72,26 +72,23 @@ impl GameObject for Noah {
         self.velocity.approach(target_velocity, 400.0 * dt);
         self.velocity.y = old_velocity_y;
 
-        let is_walking =
-            if target_velocity.len() > 0.1 {
-                self.direction =
-                    if target_velocity.x.abs() > target_velocity.y.abs() {
-                        i32::from(target_velocity.x > 0.0)
-                    } else {
-                        1
-                    };
-
-                true
-            } else {
-                false
-            };
+        if target_velocity.len() > 0.1 {
+            self.direction =
+                if target_velocity.x.abs() > target_velocity.y.abs() {
+                    i32::from(target_velocity.x > 0.0)
+                } else {
+                    1
+                };
+        }
 
         if self.jump_trigger.poll(ctx) && self.jump_timer > 0.0 {
             self.velocity.y -= 400.0;
             self.jump_timer = -1.0;
         }
 
-        let mode = self.direction + if is_walking { 2 } else { 0 };
+        // The sheet only has the walk cycle, one row per direction. Standing
+        // still just stops the frames.
+        let mode = self.direction;
 
         let mut sprite_transform = self.transform.clone();
         let collision_height = self.collision_size.y;
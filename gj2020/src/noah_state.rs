@@ -1,9 +1,9 @@
 use engine::prelude::*;
 use audio_library::AudioLibrary;
 use crate::noah::Noah;
-use crate::plank::Plank;
+use crate::plank::{Plank, PlankBroke, PlankRepaired};
 use crate::ladder::Ladder;
-use crate::ocean::Ocean;
+use crate::ocean::{Ocean, OceanRiseRate, BoatSunk};
 use crate::end_state::EndState;
 
 pub struct NoahState {
@@ -15,9 +15,21 @@ pub struct NoahState {
     broken_planks: u32,
     total_planks: u32,
     hub_state: Option<Box<dyn GameState>>,
-    planks_repaired: u32
+    planks_repaired: u32,
+    boat_sunk: bool,
+    event_handlers: EventHandlers<NoahState>,
  }
 
+fn event_handlers() -> EventHandlers<NoahState> {
+    EventHandlers::new()
+        .subscribe(|state: &mut NoahState, _: &PlankBroke, _| state.broken_planks += 1)
+        .subscribe(|state: &mut NoahState, _: &PlankRepaired, _| {
+            state.broken_planks -= 1;
+            state.planks_repaired += 1;
+        })
+        .subscribe(|state: &mut NoahState, _: &BoatSunk, _| state.boat_sunk = true)
+}
+
 impl NoahState {
     pub fn create(_ctx: &mut Engine, hub_state: Box<dyn GameState>) -> Result<Box<dyn GameState>, Error>  {
         let level = Level2D::load_from_file(_ctx, "assets/levels/Ark4.json");
@@ -92,6 +104,8 @@ impl NoahState {
                 total_planks: plank_count as u32,
                 hub_state: Some(hub_state),
                 planks_repaired: 0,
+                boat_sunk: false,
+                event_handlers: event_handlers(),
             };
         let state = Box::new(state);
 
@@ -103,27 +117,29 @@ impl NoahState {
 
 impl GameState for NoahState {
     fn update(mut self: Box<Self>, ctx: &mut Engine, _dt: f32) -> Result<Box<dyn GameState>, Error> {
+        // Only the drawn level changes, planks and ladders stay where they
+        // were placed
+        if ctx.assets_changed(self.level.get_source_files()) {
+            self.level.reload(ctx);
+        }
+
         let events = self.scene.update(ctx, None, _dt);
+
+        // The handlers need the whole state, so they are set aside meanwhile
+        let event_handlers = std::mem::take(&mut self.event_handlers);
         for event in events {
-            match event.event_type {
-                EventType::PlankBroke => {
-                    self.broken_planks += 1;
-                },
-                EventType::PlankRepaired => {
-                    self.broken_planks -= 1;
-                    self.planks_repaired += 1;
-                },
-                EventType::BoatSunk => {
-                    let mut hub_state = Some(self.hub_state.take().unwrap());
-                    let transition_state = TransitionState::new(self, move |_, _| Ok(hub_state.take().unwrap()));
-                    return Ok(Box::new(transition_state));
-                }
-                _ => {}
-            }
+            event_handlers.handle(&mut self, &event.event_type, event.sender);
+        }
+        self.event_handlers = event_handlers;
+
+        if self.boat_sunk {
+            let mut hub_state = Some(self.hub_state.take().unwrap());
+            let transition_state = TransitionState::new(self, move |_, _| Ok(hub_state.take().unwrap()));
+            return Ok(Box::new(transition_state));
         }
         println!("Broken planks {}, total planks {}", self.broken_planks, self.total_planks);
 
-        self.scene.get_mut(self.ocean_id).unwrap().on_event(EventType::OceanRiseRate {rate: self.broken_planks as f32 /self.total_planks as f32}, None);
+        self.scene.get_mut(self.ocean_id).unwrap().on_event(EventType::typed(OceanRiseRate {rate: self.broken_planks as f32 /self.total_planks as f32}), None);
 
         if ctx.key_is_down(Keycode::Q) {
             ctx.reset_sound()?;
@@ -3,6 +3,12 @@ extern crate rand;
 use std::rc::Rc;
 use engine::prelude::*;
 
+pub struct BoatSunk;
+
+pub struct OceanRiseRate {
+    pub rate: f32
+}
+
 pub struct Ocean {
     sprite: AnimatedSprite,
     transform: Transform,
@@ -62,8 +68,8 @@ impl GameObject for Ocean {
         self.set_transform(transform);
 
         if self.sprite.get_position().y < 365.0 {
-            event_mailbox.submit_event(
-                EventType::BoatSunk,
+            event_mailbox.submit_typed(
+                BoatSunk,
                 EventReceiver::Scene
             );
         }
@@ -84,7 +90,7 @@ impl GameObject for Ocean {
     }
 
     fn on_event(&mut self, event: EventType, _sender: Option<SceneObjectId>) -> bool {
-        if let EventType::OceanRiseRate{rate} = event {
+        if let Some(OceanRiseRate{rate}) = event.downcast_ref() {
             self.change = rate*42.0;
         }
         true
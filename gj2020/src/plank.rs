@@ -4,6 +4,10 @@ use std::rc::Rc;
 use engine::prelude::*;
 use self::rand::Rng;
 
+pub struct PlankBroke;
+
+pub struct PlankRepaired;
+
 pub struct Plank {
     sprite: AnimatedSprite,
     transform: Transform,
@@ -101,8 +105,8 @@ impl GameObject for Plank {
                         self.plank_state = PlankState::Broken;
                         self.toggle_texture(ctx);
 
-                        event_mailbox.submit_event(
-                            EventType::PlankBroke,
+                        event_mailbox.submit_typed(
+                            PlankBroke,
                             EventReceiver::Scene
                         );
                     }
@@ -112,8 +116,8 @@ impl GameObject for Plank {
             PlankState::Repairing => {
                 self.toggle_texture(ctx);
                 self.plank_state = PlankState::Repaired;
-                event_mailbox.submit_event(
-                    EventType::PlankRepaired,
+                event_mailbox.submit_typed(
+                    PlankRepaired,
                     EventReceiver::Scene
                 );
             },
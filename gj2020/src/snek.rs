@@ -1,17 +1,20 @@
 use std::rc::Rc;
 use engine::prelude::*;
 
+// Sent by Snek to itself once it may jump again
+struct JumpReady;
 
 pub struct Snek {
     controller: AxisController,
     interact_trigger: Trigger,
     sprite: AggregatedAnimatedSprite,
+    animation: AnimationStateMachine,
     transform: Transform,
     velocity: Vec2,
     direction: i32,
     collision_size: Vec2,
     just_colided: i32,
-    just_jumped: i32,
+    can_jump: bool,
     left_jumps: i32,
     shape: Rc<dyn CollisionShape>,
 }
@@ -31,6 +34,19 @@ impl Snek {
         sprite.add(idle_sprite);
         sprite.add(walk_sprite);
 
+        // Modes of the idle and walk sprites start at 0 and 4, one per
+        // direction
+        let mut animation = AnimationStateMachine::new("idle", 0);
+        animation.add_state("walk", 4)?;
+        animation.add_transition(
+            Transition::new("idle", "walk")
+                .with_condition(Condition::Above("input".to_string(), 0.1))
+        )?;
+        animation.add_transition(
+            Transition::new("walk", "idle")
+                .with_condition(Condition::Below("input".to_string(), 0.1))
+        )?;
+
         let collision_size = Vec2::from_coords(200.0, 200.0);
         let rect = Rect2D::centered_rectangle(collision_size);
         let square = SquareShape::from_aabb(rect);
@@ -45,12 +61,13 @@ impl Snek {
                 ),
                 interact_trigger: Trigger::new(Keycode::Space),
                 sprite,
+                animation,
                 transform: Transform::new(),
                 velocity: Vec2::new(),
                 collision_size,
                 direction: 0,
                 just_colided: 0,
-                just_jumped: 0,
+                can_jump: true,
                 left_jumps: 2,
                 shape: Rc::new(square),
             };
@@ -65,10 +82,6 @@ impl Snek {
 
 impl GameObject for Snek {
     fn update(&mut self, ctx: &mut Engine, event_mailbox: &mut dyn EventMailbox, dt: f32) -> bool {
-        if self.just_jumped != 0
-        {
-            self.just_jumped -= 1;
-        }
         let controller_input = self.controller.poll(ctx);
         let y_val = controller_input.y;
 
@@ -84,25 +97,26 @@ impl GameObject for Snek {
             if self.just_colided > 0
             {
                 self.just_colided -= 1;
-            } else if self.left_jumps > 0 && self.just_jumped == 0 {
+            } else if self.left_jumps > 0 && self.can_jump {
                 self.left_jumps -= 1;
-                self.just_jumped += 1;
                 self.velocity.y = y_val * 500.0;
+
+                if let Some(id) = event_mailbox.get_sender_id() {
+                    self.can_jump = false;
+                    event_mailbox.schedule_event(0.1, EventType::typed(JumpReady), EventReceiver::Addressed { object_id: id });
+                }
             }
         }
 
-        let is_walking =
-            if controller_input.len() > 0.1 {
-                self.direction =
-                    if controller_input.x.abs() > controller_input.y.abs() {
-                        if controller_input.x > 0.0 { 1 } else { 3 }
-                    } else if controller_input.y > 0.0 { 2 } else { 0 };
-                true
-            } else {
-                false
-            };
+        if controller_input.len() > 0.1 {
+            self.direction =
+                if controller_input.x.abs() > controller_input.y.abs() {
+                    if controller_input.x > 0.0 { 1 } else { 3 }
+                } else if controller_input.y > 0.0 { 2 } else { 0 };
+        }
 
-        let mode = self.direction + if is_walking { 4 } else { 0 };
+        self.animation.set_parameter("input", controller_input.len());
+        self.animation.update(dt);
 
         let mut sprite_transform = self.transform.clone();
         let collision_height = self.collision_size.y;
@@ -115,7 +129,7 @@ impl GameObject for Snek {
             )
         );
 
-        self.sprite.set_mode(mode);
+        self.animation.apply_with_offset(&mut self.sprite, self.direction);
         self.sprite.set_transform(&sprite_transform);
         self.sprite.step_time(dt * self.velocity.len() * 0.002);
 
@@ -146,6 +160,11 @@ impl GameObject for Snek {
     }
 
     fn on_event(&mut self, event: EventType, _sender: Option<SceneObjectId>) -> bool {
+        if event.is::<JumpReady>() {
+            self.can_jump = true;
+            return true;
+        }
+
         match event {
             EventType::Collide { force } => {
                 self.just_colided = 32;
@@ -46,6 +46,15 @@ impl SnekState {
 
 impl GameState for SnekState {
     fn update(mut self: Box<Self>, ctx: &mut Engine, dt: f32) -> Result<Box<dyn GameState>, Error> {
+        if ctx.assets_changed(self.level.get_source_files()) {
+            self.level.reload(ctx);
+
+            // The apple trees are already in the scene
+            if let Some(tile_id) = self.level.special_blocks.get("apple_tree") {
+                self.level.objects.take_tile_with_id(*tile_id);
+            }
+        }
+
         let events = self.scene.update(ctx, Some(&self.level.objects), dt);
         for event in events {
             if let EventType::Suck = event.event_type {
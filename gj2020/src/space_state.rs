@@ -1,15 +1,33 @@
 use engine::prelude::*;
 use celestial_body::*;
-use smooth_transform::*;
 use engine::game_object::EventQueue;
 
+// Fills the screen whatever the camera does, on a layer that does not move
+// or zoom with it
+struct Background {
+    sprite: StaticSprite,
+}
+
+impl GameObject for Background {
+    fn update(&mut self, ctx: &mut Engine, _event_mailbox: &mut dyn EventMailbox, _dt: f32) -> bool {
+        let x_factor = ctx.get_width() as f32 / 1280_f32;
+        let y_factor = ctx.get_height() as f32 / 720_f32;
+        self.sprite.set_scale(x_factor.min(y_factor) * ctx.get_window_scale());
+        true
+    }
+
+    fn render(&self, ctx: &mut DrawContext) {
+        self.sprite.draw(ctx);
+    }
+}
+
 pub struct SpaceState {
-    background: StaticSprite,
+    scene: Scene,
     bodies: Vec::<CelestialBody>,
     fixed: Vec::<bool>,
     fixed_timer: f32,
     event_queue: EventQueue,
-    camera: SmoothTransform,
+    camera: CameraController,
     return_to_state: Option<Box<dyn GameState>>,
 }
 
@@ -37,17 +55,21 @@ impl SpaceState {
         bodies.push(planet);
         bodies.push(planet2);
         bodies.push(planet3);
+
+        let mut scene = Scene::new();
+        let background_id = scene.add_object(Background { sprite: background });
+        scene.add_render_layer(RenderLayer::new("background", -1).with_parallax(0.0).with_zoom(1.0));
+        scene.set_render_layer(background_id, "background");
+
         let state = SpaceState {
+            scene,
             bodies,
             fixed: vec![false, false, false],
             fixed_timer: 2.0,
-            background,
             event_queue: EventQueue::new(),
-            camera: SmoothTransform::new(&Transform {
-                translation: Vec2::new(),
-                scale: 1.0,
-                angle: 0.0,
-            }, 2.0, 1.0),
+            camera: CameraController::new()
+                .with_follow_speed(2.0)
+                .with_zoom_speed(2.0),
             return_to_state: Some(return_to_state),
         };
 
@@ -98,22 +120,6 @@ impl SpaceState {
         }
     }
 
-    fn draw_bg(&mut self, ctx: &mut Engine) {
-        let camera = ctx.get_camera();
-        let x_factor = ctx.get_width() as f32 / 1280_f32;
-        let y_factor = ctx.get_height() as f32 / 720_f32;
-        let mut _factor = 1.0;
-        if x_factor < y_factor {
-            _factor = x_factor;
-        }
-        else {
-            _factor = y_factor;
-        }
-        self.background.set_position(ctx.screen_to_world((ctx.get_width()/2) as i32, (ctx.get_height()/2) as i32));
-        self.background.set_scale(_factor * camera.get_scale());
-        self.background.draw(&mut ctx.get_draw_context());
-    }
-
     fn draw_fg(&mut self, engine: &mut Engine) {
         let ctx = &mut engine.get_draw_context();
         for body in &self.bodies {
@@ -150,16 +156,16 @@ impl GameState for SpaceState {
             dt *= 10.0;
         }
 
+        // Follow the sun and zoom out as far as the furthest planet is
         let mut maxdist: f32 = 1.0;
         let origin = self.bodies[0].get_position();
         for body in &self.bodies {
             maxdist = f32::max(maxdist, (origin - body.get_position()).len());
         }
-        let camera = &mut self.camera;
-        camera.set_pan_target(origin);
-        camera.set_scale_target(maxdist / 333.0);
-        camera.update(dt);
-        ctx.set_camera(camera.get());
+        self.camera.set_target(origin);
+        self.camera.set_zoom(maxdist / 333.0);
+        self.camera.update(ctx, dt);
+        self.scene.update(ctx, None, dt);
 
         let mut physics = Vec::<CelestialBodyPhysics>::new();
         for body in &self.bodies {
@@ -173,7 +179,7 @@ impl GameState for SpaceState {
     }
 
     fn draw(&mut self, engine: &mut Engine, _dt: f32) -> Result<(), Error> {
-        self.draw_bg(engine);
+        self.scene.render(engine);
         self.draw_fg(engine);
 
         Ok(())
@@ -2,6 +2,8 @@ use engine::prelude::*;
 
 use std::f32;
 
+pub struct FreeFromDust;
+
 pub struct Dust {
     sprite: AnimatedSprite,
     transform: Transform,
@@ -38,8 +40,8 @@ impl GameObject for Dust {
     fn update(&mut self, _ctx: &mut Engine, event_mailbox: &mut dyn EventMailbox, dt: f32) -> bool {
         if self.delete_me {
 
-            event_mailbox.submit_event(
-                EventType::FreeFromDust,
+            event_mailbox.submit_typed(
+                FreeFromDust,
                 EventReceiver::Nearest {
                     origin: self.transform.get_translation(),
                     max_distance: Some(120.0)
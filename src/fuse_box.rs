@@ -4,6 +4,10 @@ use std::f32;
 
 use AudioLibrary;
 
+// Sent to the scene when the fuse box is switched, which turns the lights
+// out for good
+pub struct FuseSwitched;
+
 pub struct FuseBox {
     sprite: AnimatedSprite,
     transform: Transform,
@@ -59,10 +63,13 @@ impl FuseBox {
 
 impl GameObject for FuseBox {
 
-    fn update(&mut self, ctx: &mut Engine, _event_mailbox: &mut dyn EventMailbox, dt: f32) -> bool {
+    fn update(&mut self, ctx: &mut Engine, event_mailbox: &mut dyn EventMailbox, dt: f32) -> bool {
         if !self.active {
             self.toggle_texture(ctx);
             ctx.play(self.audio_channel);
+
+            event_mailbox.submit_typed(FuseSwitched, EventReceiver::Scene);
+
             self.active = true;
         }
 
@@ -5,6 +5,8 @@ use std::f32;
 use engine::game_object::Item;
 use engine::game_object::Items;
 
+use dust::FreeFromDust;
+
 
 pub struct Key {
     sprite: AnimatedSprite,
@@ -91,7 +93,7 @@ impl GameObject for Key {
                     false
                 }
             },
-            EventType::FreeFromDust => {
+            EventType::Custom { ref event } if event.is::<FreeFromDust>() => {
                 println!("Key became free from dust");
                 self.free_for_grabs = true;
                 true
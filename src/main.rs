@@ -20,6 +20,7 @@ mod title_screen;
 mod pause_screen;
 
 use audio_library::AudioLibrary;
+use fuse_box::FuseSwitched;
 
 enum TransitionLogicLevel {
     Active,
@@ -32,9 +33,13 @@ pub struct RunningGameState {
     mid_level: Grid2,
     player_id: SceneObjectId,
     scene: Scene,
+    lighting: Lighting,
+    flashlight: LightId,
     zoom_controller: SliderController,
     dimmer: Dimmer,
     transition_logic: TransitionLogicLevel,
+    level_index: i32,
+    level_files: Vec<String>,
     intro_played: bool,
     go_to_pause: bool
 }
@@ -42,7 +47,7 @@ pub struct RunningGameState {
 impl RunningGameState {
     fn new(ctx: &mut Engine) -> Result<Self, Error> {
         let dimmer = { Dimmer::new(ctx).with_initial_value(0.0).with_target_value(1.0) };
-        let (low_level, mid_level, scene, player_id) = { Self::load_level(ctx, 0)? };
+        let (low_level, mid_level, scene, player_id, lighting, flashlight, level_files) = { Self::load_level(ctx, 0)? };
 
         let game =
             RunningGameState {
@@ -50,6 +55,8 @@ impl RunningGameState {
                 mid_level,
                 scene,
                 player_id,
+                lighting,
+                flashlight,
                 zoom_controller: SliderController::new(
                     Keycode::Minus,
                     Keycode::Plus,
@@ -57,6 +64,8 @@ impl RunningGameState {
                 ),
                 dimmer,
                 transition_logic: TransitionLogicLevel::Active,
+                level_index: 0,
+                level_files,
                 intro_played: false,
                 go_to_pause: false,
             };
@@ -64,11 +73,14 @@ impl RunningGameState {
         Ok(game)
     }
 
-    fn load_level(ctx: &mut Engine, level_index: i32) -> Result<(Grid2, Grid2, Scene, SceneObjectId), Error> {
+    fn load_level(ctx: &mut Engine, level_index: i32)
+        -> Result<(Grid2, Grid2, Scene, SceneObjectId, Lighting, LightId, Vec<String>), Error>
+    {
 
         let levels = ["assets/levels/GroundFloor.json", "assets/levels/Basement.json"];
 
-        let level = Level::load_from_file(ctx, levels[level_index as usize], 120);
+        let level = Level::try_load_from_file(ctx, levels[level_index as usize], 120)?;
+        let level_files = level.get_source_files().to_vec();
 
         let low_level = level.ground;
         let mut mid_level = level.objects;
@@ -88,12 +100,41 @@ impl RunningGameState {
 
         let player_id = scene.add_object(player);
 
+        // Levels with baked lighting are dark until the lightmap is added.
+        // Levels without one stay fully lit, as they always were.
+        let mut lighting =
+            match level.lightmap {
+                Some(lightmap) => {
+                    // The lightmap is stretched over the width of the level
+                    let bounds = low_level.get_bounds();
+                    let scale = bounds.width() / lightmap.extent().width as f32;
+
+                    let mut lighting = Lighting::new().with_ambient(Color::RGB(30, 30, 45));
+                    lighting.set_lightmap(lightmap, bounds.min, scale);
+                    lighting
+                },
+                None => Lighting::new()
+            };
+
+        let flashlight =
+            lighting.add_light(
+                Light::cone(700.0, 0.0, std::f32::consts::PI / 3.0)
+                    .with_color(Color::RGB(255, 240, 200))
+            );
+        lighting.attach_light(flashlight, player_id, Vec2::new());
+
+        let glow = lighting.add_light(Light::point(180.0).with_intensity(0.4));
+        lighting.attach_light(glow, player_id, Vec2::new());
+
         let roombas_in_level = mid_level.take_tile_with_id(*level.special_blocks.get("roomba").unwrap());
 
         for (_, position) in roombas_in_level.iter() {
             let mut roomba = roomba::Roomba::new(ctx)?;
             roomba.get_transform_mut().set_translation(*position);
-            scene.add_object(roomba);
+            let roomba_id = scene.add_object(roomba);
+
+            let led = lighting.add_light(Light::point(60.0).with_color(Color::RGB(255, 40, 40)));
+            lighting.attach_light(led, roomba_id, Vec2::new());
         }
 
         let dust = mid_level.take_tile_with_id(*level.special_blocks.get("dust").unwrap());
@@ -130,23 +171,30 @@ impl RunningGameState {
 
 
 
-        Ok((low_level, mid_level, scene, player_id))
+        Ok((low_level, mid_level, scene, player_id, lighting, flashlight, level_files))
     }
 
-    pub fn change_level(&mut self, ctx: &mut Engine, level_index: i32) {
+    // Nothing changes if the level can not be loaded
+    pub fn change_level(&mut self, ctx: &mut Engine, level_index: i32) -> Result<(), Error> {
         let old_position =
             self.scene.get(self.player_id).unwrap().get_physical_object().unwrap().get_transform().get_translation();
 
-        let (low_level, mid_level, scene, player_id) = Self::load_level(ctx, level_index).unwrap();
+        let (low_level, mid_level, scene, player_id, lighting, flashlight, level_files) = Self::load_level(ctx, level_index)?;
 
 
         self.low_level = low_level;
         self.mid_level = mid_level;
         self.scene = scene;
         self.player_id = player_id;
+        self.lighting = lighting;
+        self.flashlight = flashlight;
+        self.level_index = level_index;
+        self.level_files = level_files;
 
         self.scene.get_mut(self.player_id).unwrap().get_physical_object_mut().unwrap().get_transform_mut().set_translation(
             old_position);
+
+        Ok(())
     }
 }
 
@@ -158,6 +206,15 @@ impl GameState for RunningGameState {
             return Ok(Box::new(pause_screen::PauseScreenState::new(ctx, self)?));
         }
 
+        // Everything in the level starts over, only the player keeps its
+        // place. Files may be half written, so errors only get logged.
+        if ctx.assets_changed(&self.level_files) {
+            let level_index = self.level_index;
+            if let Err(e) = self.change_level(ctx, level_index) {
+                println!("Could not reload level: {:?}", e);
+            }
+        }
+
         match self.transition_logic {
             TransitionLogicLevel::FadingOut { target_level: next_level } => {
                 self.dimmer.set_target(0.0);
@@ -165,7 +222,7 @@ impl GameState for RunningGameState {
                 if self.dimmer.get_value() == 0.0 {
                     self.transition_logic = TransitionLogicLevel::FadingIn { target_level: next_level };
 
-                    self.change_level(ctx, next_level);
+                    self.change_level(ctx, next_level)?;
                 }
             },
             TransitionLogicLevel::FadingIn { target_level: _next_level } => {
@@ -187,7 +244,21 @@ impl GameState for RunningGameState {
 
         ctx.set_camera_position(player_position);
 
-        self.scene.update(ctx, Some(&self.mid_level), dt);
+        let events = self.scene.update(ctx, Some(&self.mid_level), dt);
+        for event in events {
+            if event.event_type.is::<FuseSwitched>() {
+                self.lighting.set_lightmap_enabled(false);
+            }
+        }
+
+        if let Some(player) = self.scene.get_as::<player::Player>(self.player_id) {
+            let facing = player.get_facing_angle();
+            if let Some(flashlight) = self.lighting.get_light_mut(self.flashlight) {
+                flashlight.set_direction(facing);
+            }
+        }
+
+        self.lighting.update(&self.scene);
         self.dimmer.update(dt);
 
         if !self.intro_played {
@@ -213,6 +284,8 @@ impl GameState for RunningGameState {
         let mut transform = Transform::new();
         transform.set_translation(Vec2::from_coords(0.0, 0.0));
 
+        self.lighting.render(ctx)?;
+
         // let fps = (1.0 / dt) as i32;
         self.dimmer.draw(ctx);
 
@@ -261,6 +334,14 @@ impl GameInterface for GoogleHomeopathicMedicine {
     }
 
     fn create_starting_state(ctx: &mut Engine) -> Result<Box<dyn GameState>, Error> {
+        if std::env::var("HOT_RELOAD").is_ok() {
+            ctx.enable_hot_reload();
+        }
+
+        if std::env::var("SCREENSHOTS").is_ok() {
+            ctx.get_frame_capture().set_screenshot_key(Some(Keycode::F12));
+        }
+
         let mut sounds = HashMap::new();
         sounds.insert(AudioLibrary::Music, "assets/music/home_automation.wav");
         sounds.insert(AudioLibrary::AccidentSong, "assets/music/would_you_like_to_hear_a_song.wav");
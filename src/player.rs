@@ -67,6 +67,16 @@ impl Player {
     pub fn get_transform_mut(&mut self) -> &mut Transform {
         &mut self.transform
     }
+
+    // The way the player is looking in radians, 0 is right and PI/2 is down
+    pub fn get_facing_angle(&self) -> f32 {
+        match self.direction {
+            0 => -std::f32::consts::FRAC_PI_2,
+            2 => std::f32::consts::FRAC_PI_2,
+            3 => std::f32::consts::PI,
+            _ => 0.0
+        }
+    }
 }
 
 impl GameObject for Player {
@@ -159,6 +169,8 @@ impl GameObject for Player {
         Some(self)
     }
 
+    fn get_tags(&self) -> &[&'static str] { &["player"] }
+
     fn on_event(&mut self, event: EventType, sender: Option<SceneObjectId>) -> bool {
         // For event debugging
         //println!("PLAYER: {:?}", event);
@@ -61,11 +61,18 @@ impl GameObject for Roomba {
     fn update(&mut self, ctx: &mut Engine, event_mailbox: &mut dyn EventMailbox, dt: f32) -> bool {
 
         if self.mode == RoombaState::Searching {
-            event_mailbox.submit_event(
-                EventType::Probe { hint: "player".to_string() },
-                EventReceiver::Broadcast
-            );
-            self.mode = RoombaState::Random;
+            let origin = self.transform.get_translation();
+
+            let player =
+                event_mailbox
+                    .get_scene_index()
+                    .and_then(|index| index.nearest_with_tag(origin, "player"));
+
+            self.mode =
+                match player {
+                    Some((_, position)) => RoombaState::Investigating(position),
+                    None => RoombaState::Random
+                };
         }
 
         if self.mode == RoombaState::Attacking {